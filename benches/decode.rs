@@ -0,0 +1,121 @@
+//! Decode one query page column by column and compare it with the previous row by row decoding,
+//! run with `cargo bench --features bench`
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use iotdb::{decode_page, DataType, Field, ValueRow};
+
+type Page = (Vec<u8>, Vec<Vec<u8>>, Vec<Vec<u8>>);
+
+const DATA_TYPES: [DataType; 4] = [
+    DataType::BOOLEAN,
+    DataType::INT64,
+    DataType::DOUBLE,
+    DataType::TEXT,
+];
+
+/// A page without nulls, the previous decoding only read the first byte of every bitmap
+fn page(rows: usize) -> Page {
+    let time = (0..rows as i64).flat_map(i64::to_be_bytes).collect();
+    let booleans = (0..rows).map(|row| (row % 2) as u8).collect();
+    let longs = (0..rows as i64).flat_map(i64::to_be_bytes).collect();
+    let doubles = (0..rows)
+        .flat_map(|row| (row as f64 / 2.0).to_be_bytes())
+        .collect();
+    let mut texts = vec![];
+    for row in 0..rows {
+        let text = format!("value {}", row);
+        texts.extend_from_slice(&(text.len() as i32).to_be_bytes());
+        texts.extend_from_slice(text.as_bytes());
+    }
+    let bitmaps = vec![vec![0xFF; rows.div_ceil(8)]; DATA_TYPES.len()];
+    (time, vec![booleans, longs, doubles, texts], bitmaps)
+}
+
+/// The decoding before pages were decoded column by column, every row drains its values
+/// from the front of the column buffers
+fn decode_rows(data_types: &[DataType], page: Page) -> Vec<ValueRow> {
+    const FLAG: i32 = 0x80;
+    let (time, mut value_list, bitmap_list) = page;
+
+    let mut values: Vec<ValueRow> = Vec::new();
+    let mut row_num = 0;
+    loop {
+        let sum_len: usize = value_list.iter().map(|value| value.len()).sum();
+        if sum_len == 0 {
+            break;
+        }
+
+        let mut value_row = ValueRow::new();
+        let mut time = time.clone();
+        if !time.is_empty() {
+            let bytes: Vec<u8> = time.drain(..8).collect();
+            value_row.set_timestamp(i64::from_be_bytes(bytes.try_into().unwrap()));
+        }
+
+        for (col_index, data_type) in data_types.iter().enumerate() {
+            let mut field = Field::new(*data_type);
+            let bitmap = bitmap_list[col_index][0] as i32;
+            let is_null = ((FLAG >> (row_num % 8)) & (bitmap & 0xFF)) == 0;
+
+            if !is_null {
+                let values = &mut value_list[col_index];
+                match data_type {
+                    DataType::BOOLEAN => {
+                        field.bool_value = Some(values[0].eq(&1));
+                        values.remove(0);
+                    }
+                    DataType::INT32 => {
+                        let bytes: Vec<u8> = values.drain(..4).collect();
+                        field.int_value = Some(i32::from_be_bytes(bytes.try_into().unwrap()));
+                    }
+                    DataType::INT64 => {
+                        let bytes: Vec<u8> = values.drain(..8).collect();
+                        field.long_value = Some(i64::from_be_bytes(bytes.try_into().unwrap()));
+                    }
+                    DataType::FLOAT => {
+                        let bytes: Vec<u8> = values.drain(..4).collect();
+                        field.float_value = Some(f32::from_be_bytes(bytes.try_into().unwrap()));
+                    }
+                    DataType::DOUBLE => {
+                        let bytes: Vec<u8> = values.drain(..8).collect();
+                        field.double_value = Some(f64::from_be_bytes(bytes.try_into().unwrap()));
+                    }
+                    DataType::TEXT => {
+                        let bytes: Vec<u8> = values.drain(..4).collect();
+                        let length = i32::from_be_bytes(bytes.try_into().unwrap()) as usize;
+                        field.binary_value = Some(values.drain(..length).collect());
+                    }
+                }
+            }
+            value_row.add_field(field);
+        }
+        row_num += 1;
+        values.push(value_row);
+    }
+    values
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_page");
+    for rows in [1024, 8192] {
+        let page = page(rows);
+        group.bench_with_input(BenchmarkId::new("columns", rows), &page, |b, page| {
+            b.iter_batched(
+                || page.clone(),
+                |(time, values, bitmaps)| decode_page(&DATA_TYPES, time, values, bitmaps).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("rows", rows), &page, |b, page| {
+            b.iter_batched(
+                || page.clone(),
+                |page| decode_rows(&DATA_TYPES, page),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
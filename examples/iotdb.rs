@@ -35,7 +35,9 @@ fn main() -> Result<(), anyhow::Error> {
         Compressor::default(),
     )?;
 
-    let now = Local::now().timestamp_millis();
+    let now = session.to_timestamp(&Local::now())?;
+
+    let second = session.timestamp_precision().units_per_second();
     session.sql(
         format!(
             "INSERT INTO root.ln.wf01.wt01(timestamp,status) values({},true)",
@@ -46,29 +48,29 @@ fn main() -> Result<(), anyhow::Error> {
     session.sql(
         format!(
             "INSERT INTO root.ln.wf01.wt01(timestamp,status) values({},false)",
-            now + 1000
+            now + second
         )
         .as_str(),
     )?;
     session.sql(
         format!(
             "INSERT INTO root.ln.wf01.wt01(timestamp,status,temperature) values({},false,18.36)",
-            now + 2000
+            now + 2 * second
         )
         .as_str(),
     )?;
     session.sql(
         format!(
             "INSERT INTO root.ln.wf01.wt01(timestamp,status,temperature) values({},true,32.23)",
-            now + 3000
+            now + 3 * second
         )
         .as_str(),
     )?;
     session.sql("select * from root.ln")?.show();
 
-    // DF (TODO)
+    // DataFrame
     let df = session.sql("select * from root.ln")?.to_df()?;
-    println!("IoTDB DF is empty: {}", df.is_empty());
+    println!("{}", df);
 
     session.close()?;
 
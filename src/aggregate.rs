@@ -0,0 +1,413 @@
+//! Typed aggregation queries
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::errors::IotDBError;
+use crate::query::{join_path, value_literal};
+use crate::timestamp::Timestamp;
+use crate::value::Value;
+use crate::DataType;
+
+/// Aggregation functions of IoTDB
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Aggregation {
+    Count,
+    Sum,
+    Avg,
+    Extreme,
+    MaxValue,
+    MinValue,
+    FirstValue,
+    LastValue,
+    MaxTime,
+    MinTime,
+}
+
+impl Aggregation {
+    /// Name of the function in statements and result columns
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregation::Count => "count",
+            Aggregation::Sum => "sum",
+            Aggregation::Avg => "avg",
+            Aggregation::Extreme => "extreme",
+            Aggregation::MaxValue => "max_value",
+            Aggregation::MinValue => "min_value",
+            Aggregation::FirstValue => "first_value",
+            Aggregation::LastValue => "last_value",
+            Aggregation::MaxTime => "max_time",
+            Aggregation::MinTime => "min_time",
+        }
+    }
+
+    /// Whether the function aggregates the timestamps, these are listed in
+    /// `ServerProperties.supported_time_aggregation_operations`
+    pub fn is_time_aggregation(&self) -> bool {
+        matches!(self, Aggregation::MaxTime | Aggregation::MinTime)
+    }
+}
+
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [
+            Aggregation::Count,
+            Aggregation::Sum,
+            Aggregation::Avg,
+            Aggregation::Extreme,
+            Aggregation::MaxValue,
+            Aggregation::MinValue,
+            Aggregation::FirstValue,
+            Aggregation::LastValue,
+            Aggregation::MaxTime,
+            Aggregation::MinTime,
+        ]
+        .into_iter()
+        .find(|aggregation| aggregation.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("This '{}' aggregation doesn't exist", name))
+    }
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How empty windows of a `GROUP BY` time query are filled
+#[derive(Clone, Debug, PartialEq)]
+pub enum FillMethod {
+    Previous,
+    PreviousUntilLast,
+    Linear,
+    Value(Value),
+}
+
+/// An aggregation query, run it with [`Session::aggregate`](crate::Session::aggregate).
+///
+/// ```
+/// use std::time::Duration;
+/// use iotdb::{AggregateQuery, Aggregation, FillMethod};
+///
+/// let query = AggregateQuery::new(&["root", "ln", "wf01", "wt01"])
+///     .select(Aggregation::Avg, &["temperature"])
+///     .group_by_time(0, 86_400_000, Duration::from_secs(3600))
+///     .fill(FillMethod::Previous);
+/// assert_eq!(
+///     query.to_sql().unwrap(),
+///     "SELECT avg(temperature) FROM root.ln.wf01.wt01 \
+///      GROUP BY ([0, 86400000), 3600000ms) FILL(ALL[PREVIOUS])"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AggregateQuery {
+    prefix_path: String,
+    selections: Vec<(Aggregation, String)>,
+    time_range: Option<(i64, i64)>,
+    window: Option<(i64, i64, Duration)>,
+    sliding: Option<Duration>,
+    level: Option<usize>,
+    fills: Vec<(Option<DataType>, FillMethod)>,
+    limit: Option<(usize, usize)>,
+    series_limit: Option<(usize, usize)>,
+}
+
+impl AggregateQuery {
+    /// Aggregate the series below the prefix path, e.g. `["root", "ln", "wf01", "wt01"]`,
+    /// nodes are quoted like in [`Query::from`](crate::Query::from)
+    pub fn new<S: AsRef<str>>(nodes: &[S]) -> Self {
+        Self {
+            prefix_path: join_path(nodes),
+            ..Self::default()
+        }
+    }
+
+    /// Aggregate the suffix path below the prefix path, e.g. `["temperature"]` or `["*"]`
+    pub fn select<S: AsRef<str>>(mut self, aggregation: Aggregation, nodes: &[S]) -> Self {
+        self.selections.push((aggregation, join_path(nodes)));
+        self
+    }
+
+    /// Only aggregate points in `[start, end)`, in the server precision
+    pub fn time_range(mut self, start: i64, end: i64) -> Self {
+        self.time_range = Some((start, end));
+        self
+    }
+
+    /// `GROUP BY ([start, end), interval)` windows, the times are in the server precision
+    pub fn group_by_time(mut self, start: i64, end: i64, interval: Duration) -> Self {
+        self.window = Some((start, end, interval));
+        self
+    }
+
+    /// Step between the `GROUP BY` time windows, the interval if unset
+    pub fn sliding(mut self, sliding: Duration) -> Self {
+        self.sliding = Some(sliding);
+        self
+    }
+
+    /// `GROUP BY LEVEL`, aggregate the series of the same node at `level`
+    pub fn level(mut self, level: usize) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Fill the empty time windows of all data types
+    pub fn fill(mut self, method: FillMethod) -> Self {
+        self.fills.push((None, method));
+        self
+    }
+
+    /// Fill the empty time windows of the series of `data_type`
+    pub fn fill_type(mut self, data_type: DataType, method: FillMethod) -> Self {
+        self.fills.push((Some(data_type), method));
+        self
+    }
+
+    /// `LIMIT` and `OFFSET` of the rows
+    pub fn limit(mut self, limit: usize, offset: usize) -> Self {
+        self.limit = Some((limit, offset));
+        self
+    }
+
+    /// `SLIMIT` and `SOFFSET` of the series
+    pub fn series_limit(mut self, limit: usize, offset: usize) -> Self {
+        self.series_limit = Some((limit, offset));
+        self
+    }
+
+    pub fn aggregations(&self) -> impl Iterator<Item = Aggregation> + '_ {
+        self.selections.iter().map(|(aggregation, _)| *aggregation)
+    }
+
+    pub(crate) fn is_grouped_by_time(&self) -> bool {
+        self.window.is_some()
+    }
+
+    /// Build the statement, invalid combinations of clauses are rejected
+    pub fn to_sql(&self) -> Result<String, IotDBError> {
+        let invalid = |reason: &str| Err(IotDBError::Conversion(reason.to_string()));
+        if self.selections.is_empty() {
+            return invalid("aggregation query selects nothing");
+        }
+        if self.window.is_none() && (self.sliding.is_some() || !self.fills.is_empty()) {
+            return invalid("sliding and fill need GROUP BY time windows");
+        }
+        let durations = self.window.iter().map(|(_, _, interval)| interval);
+        if durations.chain(&self.sliding).any(Duration::is_zero) {
+            return invalid("GROUP BY intervals must be positive");
+        }
+
+        let mut sql = String::from("SELECT ");
+        for (index, (aggregation, path)) in self.selections.iter().enumerate() {
+            if index > 0 {
+                sql.push_str(", ");
+            }
+            sql.push_str(&format!("{}({})", aggregation, path));
+        }
+        sql.push_str(&format!(" FROM {}", self.prefix_path));
+        if let Some((start, end)) = self.time_range {
+            sql.push_str(&format!(" WHERE time >= {} AND time < {}", start, end));
+        }
+
+        let mut group_by = vec![];
+        if let Some((start, end, interval)) = self.window {
+            let mut window = format!("([{}, {}), {}", start, end, duration_literal(interval));
+            if let Some(sliding) = self.sliding {
+                window.push_str(&format!(", {}", duration_literal(sliding)));
+            }
+            window.push(')');
+            group_by.push(window);
+        }
+        if let Some(level) = self.level {
+            group_by.push(format!("LEVEL = {}", level));
+        }
+        if !group_by.is_empty() {
+            sql.push_str(&format!(" GROUP BY {}", group_by.join(", ")));
+        }
+
+        if !self.fills.is_empty() {
+            let fills = self
+                .fills
+                .iter()
+                .map(|(data_type, method)| {
+                    let data_type = match data_type {
+                        None => "ALL".to_string(),
+                        Some(data_type) => format!("{:?}", data_type),
+                    };
+                    fill_literal(method).map(|method| format!("{}[{}]", data_type, method))
+                })
+                .collect::<Option<Vec<_>>>();
+            let fills = match fills {
+                None => return invalid("FILL values can't be null"),
+                Some(fills) => fills,
+            };
+            sql.push_str(&format!(" FILL({})", fills.join(", ")));
+        }
+        if let Some((limit, offset)) = self.limit {
+            sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+        }
+        if let Some((limit, offset)) = self.series_limit {
+            sql.push_str(&format!(" SLIMIT {} SOFFSET {}", limit, offset));
+        }
+        Ok(sql)
+    }
+}
+
+/// Duration in the largest unit which keeps it exact, e.g. `3600000ms`
+fn duration_literal(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    match (nanos % 1_000_000, nanos % 1_000) {
+        (0, _) => format!("{}ms", nanos / 1_000_000),
+        (_, 0) => format!("{}us", nanos / 1_000),
+        _ => format!("{}ns", nanos),
+    }
+}
+
+/// Literal of the fill method, `None` for values without a SQL literal like null
+fn fill_literal(method: &FillMethod) -> Option<String> {
+    match method {
+        FillMethod::Previous => Some("PREVIOUS".to_string()),
+        FillMethod::PreviousUntilLast => Some("PREVIOUSUNTILLAST".to_string()),
+        FillMethod::Linear => Some("LINEAR".to_string()),
+        FillMethod::Value(value) => value_literal(value),
+    }
+}
+
+/// Results of an aggregation query keyed by the aggregation and the path
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AggregateResult {
+    /// Keys of the result columns in their order
+    columns: Vec<(Aggregation, String)>,
+    times: Vec<Timestamp>,
+    values: BTreeMap<(Aggregation, String), Vec<Value>>,
+}
+
+impl AggregateResult {
+    pub(crate) fn new(columns: &[String]) -> Result<Self, IotDBError> {
+        let columns = columns
+            .iter()
+            .map(|column| parse_column(column))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            values: columns.iter().map(|key| (key.clone(), vec![])).collect(),
+            columns,
+            times: vec![],
+        })
+    }
+
+    /// Append the values of one row, `time` is `None` unless the query groups by time windows
+    pub(crate) fn push_row(&mut self, time: Option<Timestamp>, row: Vec<Value>) {
+        self.times.extend(time);
+        for (key, value) in self.columns.iter().zip(row) {
+            if let Some(values) = self.values.get_mut(key) {
+                values.push(value);
+            }
+        }
+    }
+
+    /// Start times of the `GROUP BY` windows, empty for queries without time windows
+    pub fn times(&self) -> &[Timestamp] {
+        &self.times
+    }
+
+    /// Values of `aggregation(path)`, one per row, `path` is the full path in the result
+    pub fn get(&self, aggregation: Aggregation, path: &str) -> Option<&[Value]> {
+        self.values
+            .get(&(aggregation, path.to_string()))
+            .map(Vec::as_slice)
+    }
+
+    /// Value of `aggregation(path)` in the first row, for queries without time windows
+    pub fn value(&self, aggregation: Aggregation, path: &str) -> Option<&Value> {
+        self.get(aggregation, path).and_then(<[Value]>::first)
+    }
+
+    /// Iterate the aggregations, paths and values of the result columns
+    pub fn iter(&self) -> impl Iterator<Item = (Aggregation, &str, &[Value])> {
+        self.values
+            .iter()
+            .map(|((aggregation, path), values)| (*aggregation, path.as_str(), values.as_slice()))
+    }
+}
+
+/// Split a result column like `count(root.sg.d1.s1)` into the aggregation and the path
+fn parse_column(column: &str) -> Result<(Aggregation, String), IotDBError> {
+    let invalid = || IotDBError::Conversion(format!("'{}' is not an aggregation column", column));
+    let (name, path) = column.split_once('(').ok_or_else(invalid)?;
+    let path = path.strip_suffix(')').ok_or_else(invalid)?;
+    let aggregation = name.trim().parse().map_err(IotDBError::Conversion)?;
+    Ok((aggregation, path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::TimestampPrecision;
+
+    #[test]
+    fn aggregate_query_to_sql() {
+        let query = AggregateQuery::new(&["root", "sg", "*"])
+            .select(Aggregation::Count, &["s1"])
+            .select(Aggregation::MaxTime, &["s2"])
+            .group_by_time(0, 100, Duration::from_millis(10))
+            .sliding(Duration::from_micros(5500))
+            .level(1)
+            .fill_type(DataType::TEXT, FillMethod::Value(Value::from("it's")))
+            .fill(FillMethod::Linear)
+            .limit(10, 2)
+            .series_limit(5, 0);
+        assert_eq!(
+            query.to_sql().unwrap(),
+            "SELECT count(s1), max_time(s2) FROM root.sg.* \
+             GROUP BY ([0, 100), 10ms, 5500us), LEVEL = 1 \
+             FILL(TEXT['it''s'], ALL[LINEAR]) LIMIT 10 OFFSET 2 SLIMIT 5 SOFFSET 0"
+        );
+
+        let query = AggregateQuery::new(&["root", "sg", "dev-01"])
+            .select(Aggregation::Avg, &["s1"])
+            .time_range(1, 2);
+        assert_eq!(
+            query.to_sql().unwrap(),
+            "SELECT avg(s1) FROM root.sg.`dev-01` WHERE time >= 1 AND time < 2"
+        );
+        assert!(query.fill(FillMethod::Previous).to_sql().is_err());
+        assert!(AggregateQuery::new(&["root", "sg", "d1"]).to_sql().is_err());
+        let query = AggregateQuery::new(&["root", "sg", "d1"])
+            .select(Aggregation::Count, &["s1) FROM root.secret.d1 --"]);
+        assert_eq!(
+            query.to_sql().unwrap(),
+            "SELECT count(`s1) FROM root.secret.d1 --`) FROM root.sg.d1"
+        );
+    }
+
+    #[test]
+    fn aggregate_result_by_aggregation_and_path() {
+        let columns = vec![
+            "count(root.sg.d1.s1)".to_string(),
+            "MAX_VALUE(root.sg.d1.s1)".to_string(),
+        ];
+        let mut result = AggregateResult::new(&columns).unwrap();
+        for (time, count) in [(0, 3), (10, 0)] {
+            result.push_row(
+                Some(Timestamp::new(time, TimestampPrecision::Millisecond)),
+                vec![Value::Int64(count), Value::Null],
+            );
+        }
+
+        assert_eq!(result.times().len(), 2);
+        assert_eq!(
+            result.get(Aggregation::Count, "root.sg.d1.s1"),
+            Some(&[Value::Int64(3), Value::Int64(0)][..])
+        );
+        assert_eq!(
+            result.value(Aggregation::MaxValue, "root.sg.d1.s1"),
+            Some(&Value::Null)
+        );
+        assert!(AggregateResult::new(&["root.sg.d1.s1".to_string()]).is_err());
+    }
+}
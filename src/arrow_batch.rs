@@ -0,0 +1,392 @@
+//! Conversion between query results and Apache Arrow record batches
+
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array,
+    StringArray, TimestampMicrosecondArray, TimestampMillisecondArray, TimestampNanosecondArray,
+};
+use arrow::datatypes::{
+    DataType as ArrowDataType, Field as ArrowField, Float32Type, Float64Type, Int32Type, Int64Type,
+    Schema, TimeUnit, TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+    TimestampSecondType,
+};
+use arrow::record_batch::RecordBatch as ArrowRecordBatch;
+
+use crate::ds::{ColumnValues, NonAlignColumn, RecordBatch};
+use crate::errors::IotDBError;
+use crate::tablet::Tablet;
+use crate::timestamp::TimestampPrecision;
+use crate::{DataType, Field};
+
+/// Name of the timestamp column of exported batches, also looked up when inserting a batch
+pub const TIME_COLUMN: &str = "Time";
+
+fn arrow_type(data_type: DataType) -> ArrowDataType {
+    match data_type {
+        DataType::BOOLEAN => ArrowDataType::Boolean,
+        DataType::INT32 => ArrowDataType::Int32,
+        DataType::INT64 => ArrowDataType::Int64,
+        DataType::FLOAT => ArrowDataType::Float32,
+        DataType::DOUBLE => ArrowDataType::Float64,
+        DataType::TEXT => ArrowDataType::Utf8,
+    }
+}
+
+fn time_unit(precision: TimestampPrecision) -> TimeUnit {
+    match precision {
+        TimestampPrecision::Millisecond => TimeUnit::Millisecond,
+        TimestampPrecision::Microsecond => TimeUnit::Microsecond,
+        TimestampPrecision::Nanosecond => TimeUnit::Nanosecond,
+    }
+}
+
+fn time_field(name: &str, nullable: bool, precision: TimestampPrecision) -> ArrowField {
+    ArrowField::new(
+        name,
+        ArrowDataType::Timestamp(time_unit(precision), None),
+        nullable,
+    )
+}
+
+/// Build a timestamp array in the unit of the server precision
+fn time_array(
+    timestamps: impl Iterator<Item = Option<i64>>,
+    precision: TimestampPrecision,
+) -> ArrayRef {
+    match precision {
+        TimestampPrecision::Millisecond => {
+            Arc::new(TimestampMillisecondArray::from_iter(timestamps))
+        }
+        TimestampPrecision::Microsecond => {
+            Arc::new(TimestampMicrosecondArray::from_iter(timestamps))
+        }
+        TimestampPrecision::Nanosecond => Arc::new(TimestampNanosecondArray::from_iter(timestamps)),
+    }
+}
+
+/// Build an arrow array from the values starting at `offset`, null values stay null
+fn values_to_array(values: &ColumnValues, offset: usize) -> ArrayRef {
+    match values {
+        ColumnValues::Boolean(values) => Arc::new(BooleanArray::from(values[offset..].to_vec())),
+        ColumnValues::Int32(values) => Arc::new(Int32Array::from(values[offset..].to_vec())),
+        ColumnValues::Int64(values) => Arc::new(Int64Array::from(values[offset..].to_vec())),
+        ColumnValues::Float(values) => Arc::new(Float32Array::from(values[offset..].to_vec())),
+        ColumnValues::Double(values) => Arc::new(Float64Array::from(values[offset..].to_vec())),
+        ColumnValues::Text { data, ranges } => {
+            Arc::new(StringArray::from_iter(ranges[offset..].iter().map(
+                |range| range.map(|(start, end)| String::from_utf8_lossy(&data[start..end])),
+            )))
+        }
+    }
+}
+
+/// Build an arrow array from the fields of one column
+fn fields_to_array<'a>(data_type: DataType, fields: impl Iterator<Item = &'a Field>) -> ArrayRef {
+    match data_type {
+        DataType::BOOLEAN => Arc::new(BooleanArray::from_iter(fields.map(|f| f.bool_value))),
+        DataType::INT32 => Arc::new(Int32Array::from_iter(fields.map(|f| f.int_value))),
+        DataType::INT64 => Arc::new(Int64Array::from_iter(fields.map(|f| f.long_value))),
+        DataType::FLOAT => Arc::new(Float32Array::from_iter(fields.map(|f| f.float_value))),
+        DataType::DOUBLE => Arc::new(Float64Array::from_iter(fields.map(|f| f.double_value))),
+        DataType::TEXT => Arc::new(StringArray::from_iter(fields.map(|f| {
+            f.binary_value
+                .as_ref()
+                .map(|binary| String::from_utf8_lossy(binary))
+        }))),
+    }
+}
+
+/// Convert the rows of an aligned batch starting at its offset
+pub(crate) fn batch_to_arrow(
+    batch: &RecordBatch,
+    with_time: bool,
+) -> Result<ArrowRecordBatch, IotDBError> {
+    let mut fields = Vec::with_capacity(batch.columns.len() + 1);
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(batch.columns.len() + 1);
+    if with_time {
+        fields.push(time_field(TIME_COLUMN, false, batch.precision));
+        arrays.push(time_array(
+            batch.timestamps[batch.offset..].iter().copied().map(Some),
+            batch.precision,
+        ));
+    }
+    for (name, values) in batch.columns.iter().zip(&batch.values) {
+        fields.push(ArrowField::new(name, arrow_type(values.data_type()), true));
+        arrays.push(values_to_array(values, batch.offset));
+    }
+
+    Ok(ArrowRecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        arrays,
+    )?)
+}
+
+/// Convert non-aligned columns into `Time(<column>)` and value column pairs,
+/// shorter columns are padded with nulls to the length of the longest one
+pub(crate) fn non_align_to_arrow(
+    columns: &[NonAlignColumn],
+    precision: TimestampPrecision,
+) -> Result<ArrowRecordBatch, IotDBError> {
+    let height = columns.iter().map(NonAlignColumn::len).max().unwrap_or(0);
+
+    let mut fields = Vec::with_capacity(columns.len() * 2);
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(columns.len() * 2);
+    for column in columns {
+        let padding = height - column.len();
+        let null = Field::new(column.data_type());
+
+        fields.push(time_field(
+            &format!("{}({})", TIME_COLUMN, column.name()),
+            true,
+            precision,
+        ));
+        arrays.push(time_array(
+            column
+                .timestamps()
+                .iter()
+                .map(|timestamp| Some(*timestamp))
+                .chain(std::iter::repeat_n(None, padding)),
+            precision,
+        ));
+        fields.push(ArrowField::new(
+            column.name(),
+            arrow_type(column.data_type()),
+            true,
+        ));
+        arrays.push(fields_to_array(
+            column.data_type(),
+            column
+                .fields()
+                .iter()
+                .chain(std::iter::repeat_n(&null, padding)),
+        ));
+    }
+
+    Ok(ArrowRecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        arrays,
+    )?)
+}
+
+/// Build a tablet of `batch` for the device `device_id`.
+///
+/// The timestamps come from the [`TIME_COLUMN`] column, which is a timestamp or an `Int64`
+/// column in the server `precision`. Every other column is a measurement, a `<device_id>.` prefix is
+/// stripped from its name.
+pub(crate) fn batch_to_tablet(
+    device_id: &str,
+    batch: &ArrowRecordBatch,
+    precision: TimestampPrecision,
+) -> Result<Tablet, IotDBError> {
+    let schema = batch.schema();
+    let time_index = schema.index_of(TIME_COLUMN).map_err(|_| {
+        IotDBError::Conversion(format!(
+            "record batch has no '{}' column for the timestamps",
+            TIME_COLUMN
+        ))
+    })?;
+    let timestamps = server_timestamps(batch.column(time_index), precision)?;
+
+    let prefix = format!("{}.", device_id);
+    let mut measurements = vec![];
+    let mut columns = vec![];
+    for (index, (field, array)) in schema.fields().iter().zip(batch.columns()).enumerate() {
+        if index == time_index {
+            continue;
+        }
+        let values = array_to_values(array).ok_or_else(|| {
+            IotDBError::Conversion(format!(
+                "column '{}' of type {} can't be inserted",
+                field.name(),
+                array.data_type()
+            ))
+        })?;
+        let name = field.name();
+        measurements.push(name.strip_prefix(&prefix).unwrap_or(name).to_string());
+        columns.push(values);
+    }
+
+    Ok(Tablet::from_columns(
+        device_id,
+        measurements,
+        timestamps,
+        columns,
+    ))
+}
+
+/// Read a timestamp column in the server precision, nulls are rejected.
+///
+/// `Int64` values are taken as they are, timestamps of other units are rescaled.
+fn server_timestamps(
+    array: &ArrayRef,
+    precision: TimestampPrecision,
+) -> Result<Vec<i64>, IotDBError> {
+    if array.null_count() > 0 {
+        return Err(IotDBError::Conversion(format!(
+            "'{}' column has null timestamps",
+            TIME_COLUMN
+        )));
+    }
+    let (values, units_per_second) = match array.data_type() {
+        ArrowDataType::Int64 => return Ok(array.as_primitive::<Int64Type>().values().to_vec()),
+        ArrowDataType::Timestamp(TimeUnit::Second, _) => {
+            (array.as_primitive::<TimestampSecondType>().values(), 1)
+        }
+        ArrowDataType::Timestamp(TimeUnit::Millisecond, _) => (
+            array.as_primitive::<TimestampMillisecondType>().values(),
+            1_000,
+        ),
+        ArrowDataType::Timestamp(TimeUnit::Microsecond, _) => (
+            array.as_primitive::<TimestampMicrosecondType>().values(),
+            1_000_000,
+        ),
+        ArrowDataType::Timestamp(TimeUnit::Nanosecond, _) => (
+            array.as_primitive::<TimestampNanosecondType>().values(),
+            1_000_000_000,
+        ),
+        data_type => {
+            return Err(IotDBError::Conversion(format!(
+                "'{}' column of type {} is not a timestamp",
+                TIME_COLUMN, data_type
+            )))
+        }
+    };
+
+    let target = precision.units_per_second();
+    values
+        .iter()
+        .map(|value| match units_per_second < target {
+            true => value.checked_mul(target / units_per_second).ok_or_else(|| {
+                IotDBError::Conversion(format!(
+                    "timestamp {} overflows in the server precision",
+                    value
+                ))
+            }),
+            false => Ok(value.div_euclid(units_per_second / target)),
+        })
+        .collect()
+}
+
+/// Typed values of `array`, `None` if the arrow type has no IoTDB counterpart
+fn array_to_values(array: &ArrayRef) -> Option<ColumnValues> {
+    let values = match array.data_type() {
+        ArrowDataType::Boolean => ColumnValues::Boolean(array.as_boolean().iter().collect()),
+        ArrowDataType::Int32 => {
+            ColumnValues::Int32(array.as_primitive::<Int32Type>().iter().collect())
+        }
+        ArrowDataType::Int64 => {
+            ColumnValues::Int64(array.as_primitive::<Int64Type>().iter().collect())
+        }
+        ArrowDataType::Float32 => {
+            ColumnValues::Float(array.as_primitive::<Float32Type>().iter().collect())
+        }
+        ArrowDataType::Float64 => {
+            ColumnValues::Double(array.as_primitive::<Float64Type>().iter().collect())
+        }
+        ArrowDataType::Utf8 => text_values(
+            array
+                .as_string::<i32>()
+                .iter()
+                .map(|value| value.map(str::as_bytes)),
+        ),
+        ArrowDataType::LargeUtf8 => text_values(
+            array
+                .as_string::<i64>()
+                .iter()
+                .map(|value| value.map(str::as_bytes)),
+        ),
+        ArrowDataType::Binary => text_values(array.as_binary::<i32>().iter()),
+        _ => return None,
+    };
+    Some(values)
+}
+
+fn text_values<'a>(binaries: impl Iterator<Item = Option<&'a [u8]>>) -> ColumnValues {
+    let mut data = vec![];
+    let ranges = binaries
+        .map(|binary| {
+            binary.map(|binary| {
+                let start = data.len();
+                data.extend_from_slice(binary);
+                (start, data.len())
+            })
+        })
+        .collect();
+    ColumnValues::Text { data, ranges }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::TimestampSecondArray;
+
+    use super::*;
+
+    #[test]
+    fn tablet_from_batch_strips_prefix_rescales_time_and_keeps_nulls() {
+        let schema = Schema::new(vec![
+            ArrowField::new(
+                TIME_COLUMN,
+                ArrowDataType::Timestamp(TimeUnit::Second, None),
+                false,
+            ),
+            ArrowField::new("root.sg.d1.s1", ArrowDataType::Int32, true),
+            ArrowField::new("root.sg.d2.s2", ArrowDataType::Utf8, true),
+        ]);
+        let batch = ArrowRecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(TimestampSecondArray::from(vec![2, 1])),
+                Arc::new(Int32Array::from(vec![Some(20), None])),
+                Arc::new(StringArray::from(vec!["b", "a"])),
+            ],
+        )
+        .unwrap();
+
+        let tablet =
+            batch_to_tablet("root.sg.d1", &batch, TimestampPrecision::Microsecond).unwrap();
+        assert_eq!(tablet.measurements(), ["s1", "root.sg.d2.s2"]);
+        assert_eq!(tablet.data_types(), [DataType::INT32, DataType::TEXT]);
+        assert_eq!(tablet.timestamps(), [2_000_000, 1_000_000]);
+        let nulls: Vec<Vec<bool>> = tablet
+            .columns()
+            .iter()
+            .map(|column| (0..tablet.len()).map(|row| column.is_null(row)).collect())
+            .collect();
+        assert_eq!(nulls, [[false, true], [false, false]]);
+    }
+
+    #[test]
+    fn tablet_from_batch_rejects_null_timestamps() {
+        let schema = Schema::new(vec![
+            time_field(TIME_COLUMN, true, TimestampPrecision::Millisecond),
+            ArrowField::new("s1", ArrowDataType::Int32, true),
+        ]);
+        let batch = ArrowRecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(TimestampMillisecondArray::from(vec![Some(1), None])),
+                Arc::new(Int32Array::from(vec![1, 2])),
+            ],
+        )
+        .unwrap();
+
+        assert!(matches!(
+            batch_to_tablet("root.sg.d1", &batch, TimestampPrecision::Millisecond),
+            Err(IotDBError::Conversion(_))
+        ));
+    }
+
+    #[test]
+    fn server_timestamps_overflow_returns_error() {
+        let seconds: ArrayRef = Arc::new(TimestampSecondArray::from(vec![1, i64::MAX / 1_000]));
+        assert!(matches!(
+            server_timestamps(&seconds, TimestampPrecision::Nanosecond),
+            Err(IotDBError::Conversion(_))
+        ));
+        assert_eq!(
+            server_timestamps(&seconds, TimestampPrecision::Millisecond).unwrap(),
+            [1_000, i64::MAX / 1_000 * 1_000]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Cancel running queries from other threads
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use log::debug;
+
+use crate::{Config, Session};
+
+/// Cancels a query on a side connection, clones share the cancelled state and can be sent
+/// to other threads.
+///
+/// The session of the query is busy while it fetches a page, so the token opens its own
+/// session with the same config to issue `cancelOperation` and release the query.
+#[derive(Clone)]
+pub struct CancelToken {
+    config: Config,
+    query_id: i64,
+    statement_id: i64,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Leaves out the config, it holds the password of the session
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancelToken")
+            .field("query_id", &self.query_id)
+            .field("statement_id", &self.statement_id)
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl CancelToken {
+    pub(crate) fn new(config: Config, query_id: i64, statement_id: i64) -> Self {
+        Self {
+            config,
+            query_id,
+            statement_id,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn query_id(&self) -> i64 {
+        self.query_id
+    }
+
+    /// Whether the query was cancelled by this token or one of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Cancel the query and close its operation on the server, cancelling twice is a no-op.
+    ///
+    /// Fetching more rows of a cancelled query fails.
+    pub fn cancel(&self) -> anyhow::Result<()> {
+        self.cancel_with(|| {
+            let mut session = Session::connect(self.config.clone())?;
+            let result = session
+                .cancel_operation(self.query_id)
+                .and_then(|_| session.close_operation(self.query_id, self.statement_id));
+            if let Err(error) = session.close() {
+                debug!("Close cancel session failed, {}", error);
+            }
+            result
+        })
+    }
+
+    /// Set the cancelled state and run `cancel` once, the state is reset if it fails
+    fn cancel_with<F>(&self, cancel: F) -> anyhow::Result<()>
+    where
+        F: FnOnce() -> anyhow::Result<()>,
+    {
+        if self.cancelled.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+
+        let result = cancel();
+        if result.is_err() {
+            // the query still runs, so a later cancel can try again
+            self.cancelled.store(false, Ordering::Release);
+        } else {
+            debug!("Cancelled query {}", self.query_id);
+        }
+        result
+    }
+}
+
+/// Id and cancel token of a query whose results are still fetched from the server
+#[derive(Clone, Debug)]
+pub struct QueryHandle {
+    token: CancelToken,
+}
+
+impl QueryHandle {
+    pub(crate) fn new(token: CancelToken) -> Self {
+        Self { token }
+    }
+
+    pub fn query_id(&self) -> i64 {
+        self.token.query_id
+    }
+
+    pub fn cancel_token(&self) -> CancelToken {
+        self.token.clone()
+    }
+
+    /// Cancel the query, see [`CancelToken::cancel`]
+    pub fn cancel(&self) -> anyhow::Result<()> {
+        self.token.cancel()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::ConfigBuilder;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn failed_cancel_can_be_retried() {
+        assert_send_sync::<CancelToken>();
+
+        let token = CancelToken::new(Config::default(), 7, 1);
+        let clone = token.clone();
+        assert!(clone
+            .cancel_with(|| Err(anyhow!("connection refused")))
+            .is_err());
+        assert!(!token.is_cancelled());
+
+        assert!(clone.cancel_with(|| Ok(())).is_ok());
+        assert!(token.is_cancelled());
+        assert!(token
+            .cancel_with(|| panic!("cancelled queries are not cancelled again"))
+            .is_ok());
+        assert_eq!(QueryHandle::new(token).query_id(), 7);
+    }
+
+    #[test]
+    fn debug_leaves_out_the_config() {
+        let config = ConfigBuilder::new().password("secret").build();
+        let token = CancelToken::new(config, 7, 1);
+        assert_eq!(
+            format!("{:?}", token),
+            "CancelToken { query_id: 7, statement_id: 1, cancelled: false }"
+        );
+    }
+}
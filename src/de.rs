@@ -0,0 +1,279 @@
+//! Deserialize query rows into user types with serde
+
+use std::fmt;
+use std::vec;
+
+use serde::de::value::StringDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::ds::ValueRow;
+use crate::errors::IotDBError;
+use crate::value::Value;
+
+/// Name of the struct field the row timestamp is bound to
+pub const TIMESTAMP_FIELD: &str = "timestamp";
+
+impl de::Error for IotDBError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        IotDBError::Deserialize(msg.to_string())
+    }
+}
+
+pub(crate) fn from_row<T: DeserializeOwned>(row: &ValueRow) -> Result<T, IotDBError> {
+    T::deserialize(RowDeserializer { row })
+}
+
+/// Index of the column a struct field maps to, by full path first and by the last path node after that
+fn resolve(row: &ValueRow, field: &str) -> Result<Option<usize>, IotDBError> {
+    let columns = row.columns();
+    if let Some(index) = columns.iter().position(|column| column == field) {
+        return Ok(Some(index));
+    }
+
+    let mut matches = columns
+        .iter()
+        .enumerate()
+        .filter(|(_, column)| column.rsplit('.').next() == Some(field));
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Ok(Some(index)),
+        (Some((_, first)), Some((_, second))) => Err(IotDBError::Deserialize(format!(
+            "field '{}' matches both '{}' and '{}', use the full path",
+            field, first, second
+        ))),
+        _ => Ok(None),
+    }
+}
+
+struct RowDeserializer<'a> {
+    row: &'a ValueRow,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for RowDeserializer<'a> {
+    type Error = IotDBError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut entries = vec![(
+            TIMESTAMP_FIELD.to_string(),
+            Value::Int64(self.row.timestamp()),
+        )];
+        entries.extend(self.row.columns().iter().cloned().zip(self.row.values()));
+        visitor.visit_map(RowAccess::new(entries))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let mut entries = Vec::with_capacity(fields.len());
+        for field in fields {
+            if *field == TIMESTAMP_FIELD {
+                entries.push((field.to_string(), Value::Int64(self.row.timestamp())));
+            } else if let Some(index) = resolve(self.row, field)? {
+                entries.push((field.to_string(), self.row.fields()[index].value()));
+            }
+        }
+        visitor.visit_map(RowAccess::new(entries))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
+
+struct RowAccess {
+    entries: vec::IntoIter<(String, Value)>,
+    value: Option<Value>,
+}
+
+impl RowAccess {
+    fn new(entries: Vec<(String, Value)>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for RowAccess {
+    type Error = IotDBError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.entries.next() {
+            None => Ok(None),
+            Some((key, value)) => {
+                self.value = Some(value);
+                let key: StringDeserializer<IotDBError> = key.into_deserializer();
+                seed.deserialize(key).map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = self.value.take().unwrap_or(Value::Null);
+        seed.deserialize(ValueDeserializer { value })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct ValueDeserializer {
+    value: Value,
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = IotDBError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Bool(value) => visitor.visit_bool(value),
+            Value::Int32(value) => visitor.visit_i32(value),
+            Value::Int64(value) => visitor.visit_i64(value),
+            Value::Float(value) => visitor.visit_f32(value),
+            Value::Double(value) => visitor.visit_f64(value),
+            Value::Text(value) => match String::from_utf8(value) {
+                Ok(value) => visitor.visit_string(value),
+                Err(error) => visitor.visit_byte_buf(error.into_bytes()),
+            },
+            Value::Null => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Value::Text(value) => visitor.visit_byte_buf(value),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::ds::Field;
+    use crate::DataType;
+
+    fn row() -> ValueRow {
+        let mut temperature = Field::new(DataType::FLOAT);
+        temperature.float_value = Some(18.5);
+        let mut status = Field::new(DataType::BOOLEAN);
+        status.bool_value = Some(true);
+        let mut other_status = Field::new(DataType::BOOLEAN);
+        other_status.bool_value = Some(false);
+
+        let mut row = ValueRow::new();
+        row.set_timestamp(1000)
+            .set_columns(vec![
+                "root.ln.wf01.wt01.temperature".to_string(),
+                "root.ln.wf01.wt01.status".to_string(),
+                "root.ln.wf01.wt02.status".to_string(),
+                "root.ln.wf01.wt01.hardware".to_string(),
+            ])
+            .add_field(temperature)
+            .add_field(status)
+            .add_field(other_status)
+            .add_field(Field::new(DataType::TEXT));
+        row
+    }
+
+    #[test]
+    fn deserialize_struct_by_path_and_last_node() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Wt01 {
+            timestamp: i64,
+            temperature: f64,
+            #[serde(rename = "root.ln.wf01.wt01.status")]
+            status: bool,
+            hardware: Option<String>,
+            missing: Option<i32>,
+        }
+
+        let wt01: Wt01 = row().deserialize().unwrap();
+        assert_eq!(
+            wt01,
+            Wt01 {
+                timestamp: 1000,
+                temperature: 18.5,
+                status: true,
+                hardware: None,
+                missing: None,
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_ambiguous_last_node_returns_error() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Status {
+            status: bool,
+        }
+
+        assert!(matches!(
+            row().deserialize::<Status>(),
+            Err(IotDBError::Deserialize(_))
+        ));
+    }
+
+    #[test]
+    fn deserialize_type_mismatch_returns_error() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Wt01 {
+            temperature: bool,
+        }
+
+        assert!(row().deserialize::<Wt01>().is_err());
+    }
+
+    #[test]
+    fn deserialize_map_by_full_path() {
+        let mut temperature = Field::new(DataType::FLOAT);
+        temperature.float_value = Some(18.5);
+        let mut row = ValueRow::new();
+        row.set_timestamp(1000)
+            .set_columns(vec!["root.ln.wf01.wt01.temperature".to_string()])
+            .add_field(temperature);
+
+        let map: BTreeMap<String, f64> = row.deserialize().unwrap();
+        assert_eq!(map[TIMESTAMP_FIELD], 1000.0);
+        assert_eq!(map["root.ln.wf01.wt01.temperature"], 18.5);
+    }
+}
@@ -1,15 +1,39 @@
-use std::io::Cursor;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "parquet")]
+use std::{fs::File, path::Path};
 
-use byteorder::{BigEndian, ReadBytesExt};
-use chrono::{DateTime, Local, TimeZone};
-use log::debug;
-use polars::prelude::*;
-use polars::prelude::{DataFrame, Series};
-use prettytable::Row as PrettyRow;
-use prettytable::{Cell, Table};
+use anyhow::bail;
+use log::{debug, error};
+use polars::prelude::{
+    DataFrame, Int64Chunked, IntoSeries, NamedFrom, NewChunkedArray, Series, TimeUnit, Utf8Chunked,
+};
+use prettytable::Table;
 
-use crate::client::TSExecuteStatementResp;
-use crate::DataType;
+use crate::cancel::{CancelToken, QueryHandle};
+use crate::client::{
+    TIClientRPCServiceSyncClient, TSCloseOperationReq, TSExecuteStatementResp, TSFetchResultsReq,
+    TSFetchResultsResp, TSQueryDataSet, TSQueryNonAlignDataSet,
+};
+use crate::errors::IotDBError;
+use crate::export::{CsvOptions, CsvWriter, JsonOptions, JsonWriter};
+#[cfg(feature = "parquet")]
+use crate::parquet::ParquetOptions;
+use crate::render::{TableOptions, TableRenderer};
+use crate::schema::Schema;
+use crate::stats::{ColumnStats, TimeReport};
+use crate::time_zone::{is_valid_time_format, SessionTimeZone};
+use crate::timestamp::{Timestamp, TimestampPrecision};
+use crate::tracing::TracingInfo;
+use crate::value::{FromValue, Value};
+use crate::{ClientType, DataType, SUCCESS_CODE};
 
 #[derive(Clone, Debug)]
 pub struct Field {
@@ -34,11 +58,34 @@ impl Field {
             binary_value: None,
         }
     }
+
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.value().is_null()
+    }
+
+    /// Typed value of the field, [`Value::Null`] if the field has no value
+    pub fn value(&self) -> Value {
+        match self.data_type {
+            DataType::BOOLEAN => self.bool_value.into(),
+            DataType::INT32 => self.int_value.into(),
+            DataType::INT64 => self.long_value.into(),
+            DataType::FLOAT => self.float_value.into(),
+            DataType::DOUBLE => self.double_value.into(),
+            DataType::TEXT => self.binary_value.clone().into(),
+        }
+    }
 }
 
+/// One row of an aligned query result
 #[derive(Clone, Debug, Default)]
 pub struct ValueRow {
     timestamp: i64,
+    precision: TimestampPrecision,
+    columns: Arc<Vec<String>>,
     fields: Vec<Field>,
 }
 
@@ -47,52 +94,630 @@ impl ValueRow {
         Self::default()
     }
 
-    pub fn timestamp(&mut self, timestamp: i64) -> &mut ValueRow {
+    pub fn set_timestamp(&mut self, timestamp: i64) -> &mut ValueRow {
         self.timestamp = timestamp;
         self
     }
 
+    pub fn set_columns(&mut self, columns: Vec<String>) -> &mut ValueRow {
+        self.columns = Arc::new(columns);
+        self
+    }
+
     pub fn add_field(&mut self, field: Field) -> &mut ValueRow {
         self.fields.push(field);
         self
     }
+
+    /// Raw timestamp in the precision of the server
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn time(&self) -> Timestamp {
+        Timestamp::new(self.timestamp, self.precision)
+    }
+
+    /// Column names, in the same order as the fields
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn values(&self) -> Vec<Value> {
+        self.fields.iter().map(Field::value).collect()
+    }
+
+    /// Value of the column, `None` if the row has no such column
+    pub fn value(&self, column: &str) -> Option<Value> {
+        self.index_of(column)
+            .map(|index| self.fields[index].value())
+    }
+
+    /// Value of the column converted into `T`, e.g. `row.get::<f64>("root.sg.d1.s1")`
+    pub fn get<T: FromValue>(&self, column: &str) -> Result<T, IotDBError> {
+        match self.index_of(column) {
+            None => Err(IotDBError::ColumnNotFound(column.to_string())),
+            Some(index) => self.get_by_index(index),
+        }
+    }
+
+    /// Value of the column at `index` converted into `T`
+    pub fn get_by_index<T: FromValue>(&self, index: usize) -> Result<T, IotDBError> {
+        match self.fields.get(index) {
+            None => Err(IotDBError::ColumnNotFound(format!("#{}", index))),
+            Some(field) => T::from_value(&field.value()).map_err(|error| match error {
+                IotDBError::Conversion(reason) => IotDBError::Conversion(format!(
+                    "column '{}', {}",
+                    self.columns.get(index).map_or("", String::as_str),
+                    reason
+                )),
+                error => error,
+            }),
+        }
+    }
+
+    /// Deserialize the row into `T`.
+    ///
+    /// Struct fields map to columns by full path (e.g. `#[serde(rename = "root.sg.d1.s1")]`)
+    /// or by the last path node, the field named [`TIMESTAMP_FIELD`](crate::TIMESTAMP_FIELD)
+    /// gets the row timestamp.
+    #[cfg(feature = "serde")]
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T, IotDBError> {
+        crate::de::from_row(self)
+    }
+
+    fn index_of(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|name| name == column)
+    }
 }
 
+/// Typed values of one column, null values are `None`
 #[derive(Clone, Debug)]
-pub struct RecordBatch {
-    columns: Vec<String>,
-    values: Vec<ValueRow>,
-    is_empty: bool,
+pub(crate) enum ColumnValues {
+    Boolean(Vec<Option<bool>>),
+    Int32(Vec<Option<i32>>),
+    Int64(Vec<Option<i64>>),
+    Float(Vec<Option<f32>>),
+    Double(Vec<Option<f64>>),
+    /// TEXT bytes are stored contiguously, each value is a `(start, end)` range of `data`
+    Text {
+        data: Vec<u8>,
+        ranges: Vec<Option<(usize, usize)>>,
+    },
 }
 
-impl Default for RecordBatch {
-    fn default() -> Self {
-        Self {
-            columns: vec![],
-            values: vec![],
-            is_empty: true,
+/// Decode the fixed width values of a column buffer with `$read`
+macro_rules! decode_values {
+    ($values:expr, $buffer:expr, $bitmap:expr, $rows:expr, $read:expr) => {{
+        let mut pos = 0;
+        $values.reserve($rows);
+        for row in 0..$rows {
+            if is_null($bitmap, row) {
+                $values.push(None);
+            } else {
+                $values.push(Some($read(read_array($buffer, &mut pos)?)));
+            }
+        }
+    }};
+}
+
+impl ColumnValues {
+    pub(crate) fn new(data_type: DataType) -> Self {
+        match data_type {
+            DataType::BOOLEAN => ColumnValues::Boolean(vec![]),
+            DataType::INT32 => ColumnValues::Int32(vec![]),
+            DataType::INT64 => ColumnValues::Int64(vec![]),
+            DataType::FLOAT => ColumnValues::Float(vec![]),
+            DataType::DOUBLE => ColumnValues::Double(vec![]),
+            DataType::TEXT => ColumnValues::Text {
+                data: vec![],
+                ranges: vec![],
+            },
+        }
+    }
+
+    pub(crate) fn data_type(&self) -> DataType {
+        match self {
+            ColumnValues::Boolean(_) => DataType::BOOLEAN,
+            ColumnValues::Int32(_) => DataType::INT32,
+            ColumnValues::Int64(_) => DataType::INT64,
+            ColumnValues::Float(_) => DataType::FLOAT,
+            ColumnValues::Double(_) => DataType::DOUBLE,
+            ColumnValues::Text { .. } => DataType::TEXT,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = ColumnValues::new(self.data_type());
+    }
+
+    /// Keep the first `rows` values
+    fn truncate(&mut self, rows: usize) {
+        match self {
+            ColumnValues::Boolean(values) => values.truncate(rows),
+            ColumnValues::Int32(values) => values.truncate(rows),
+            ColumnValues::Int64(values) => values.truncate(rows),
+            ColumnValues::Float(values) => values.truncate(rows),
+            ColumnValues::Double(values) => values.truncate(rows),
+            ColumnValues::Text { ranges, .. } => ranges.truncate(rows),
+        }
+    }
+
+    /// Decode `rows` values of a column buffer, rows whose bitmap bit is unset are null
+    fn decode(&mut self, buffer: &[u8], bitmap: &[u8], rows: usize) -> Result<(), IotDBError> {
+        if bitmap.len() * 8 < rows {
+            return Err(IotDBError::Decode(format!(
+                "bitmap of {} bytes can't cover {} rows",
+                bitmap.len(),
+                rows
+            )));
+        }
+
+        match self {
+            ColumnValues::Boolean(values) => {
+                decode_values!(values, buffer, bitmap, rows, |bytes: [u8; 1]| bytes[0] == 1)
+            }
+            ColumnValues::Int32(values) => {
+                decode_values!(values, buffer, bitmap, rows, i32::from_be_bytes)
+            }
+            ColumnValues::Int64(values) => {
+                decode_values!(values, buffer, bitmap, rows, i64::from_be_bytes)
+            }
+            ColumnValues::Float(values) => {
+                decode_values!(values, buffer, bitmap, rows, f32::from_be_bytes)
+            }
+            ColumnValues::Double(values) => {
+                decode_values!(values, buffer, bitmap, rows, f64::from_be_bytes)
+            }
+            ColumnValues::Text { data, ranges } => {
+                // keep the whole buffer and point into it, length prefixes are skipped
+                let base = data.len();
+                let mut pos = 0;
+                ranges.reserve(rows);
+                for row in 0..rows {
+                    if is_null(bitmap, row) {
+                        ranges.push(None);
+                    } else {
+                        let length = read_length(buffer, &mut pos)?;
+                        let start = pos;
+                        read_bytes(buffer, &mut pos, length)?;
+                        ranges.push(Some((base + start, base + pos)));
+                    }
+                }
+                data.extend_from_slice(&buffer[..pos]);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_null(&self, row: usize) -> bool {
+        match self {
+            ColumnValues::Boolean(values) => values[row].is_none(),
+            ColumnValues::Int32(values) => values[row].is_none(),
+            ColumnValues::Int64(values) => values[row].is_none(),
+            ColumnValues::Float(values) => values[row].is_none(),
+            ColumnValues::Double(values) => values[row].is_none(),
+            ColumnValues::Text { ranges, .. } => ranges[row].is_none(),
+        }
+    }
+
+    fn value(&self, row: usize) -> Value {
+        match self {
+            ColumnValues::Boolean(values) => values[row].into(),
+            ColumnValues::Int32(values) => values[row].into(),
+            ColumnValues::Int64(values) => values[row].into(),
+            ColumnValues::Float(values) => values[row].into(),
+            ColumnValues::Double(values) => values[row].into(),
+            ColumnValues::Text { data, ranges } => ranges[row]
+                .map(|(start, end)| data[start..end].to_vec())
+                .into(),
+        }
+    }
+
+    fn field(&self, row: usize) -> Field {
+        let mut field = Field::new(self.data_type());
+        match self {
+            ColumnValues::Boolean(values) => field.bool_value = values[row],
+            ColumnValues::Int32(values) => field.int_value = values[row],
+            ColumnValues::Int64(values) => field.long_value = values[row],
+            ColumnValues::Float(values) => field.float_value = values[row],
+            ColumnValues::Double(values) => field.double_value = values[row],
+            ColumnValues::Text { data, ranges } => {
+                field.binary_value = ranges[row].map(|(start, end)| data[start..end].to_vec())
+            }
+        }
+        field
+    }
+
+    /// Build a typed polars series from the values of the `rows` range
+    fn to_series(&self, name: &str, rows: Range<usize>) -> Series {
+        match self {
+            ColumnValues::Boolean(values) => Series::new(name, &values[rows]),
+            ColumnValues::Int32(values) => Series::new(name, &values[rows]),
+            ColumnValues::Int64(values) => Series::new(name, &values[rows]),
+            ColumnValues::Float(values) => Series::new(name, &values[rows]),
+            ColumnValues::Double(values) => Series::new(name, &values[rows]),
+            ColumnValues::Text { data, ranges } => {
+                let mut values: Utf8Chunked = ranges[rows]
+                    .iter()
+                    .map(|range| {
+                        range.map(|(start, end)| String::from_utf8_lossy(&data[start..end]))
+                    })
+                    .collect();
+                values.rename(name);
+                values.into_series()
+            }
+        }
+    }
+
+    /// Drop the first `rows` values
+    #[cfg(feature = "parquet")]
+    fn drain(&mut self, rows: usize) {
+        match self {
+            ColumnValues::Boolean(values) => drop(values.drain(..rows)),
+            ColumnValues::Int32(values) => drop(values.drain(..rows)),
+            ColumnValues::Int64(values) => drop(values.drain(..rows)),
+            ColumnValues::Float(values) => drop(values.drain(..rows)),
+            ColumnValues::Double(values) => drop(values.drain(..rows)),
+            ColumnValues::Text { data, ranges } => {
+                ranges.drain(..rows);
+                // values are appended in row order, so the first kept value starts the data
+                let cut = ranges
+                    .iter()
+                    .find_map(|range| range.map(|(start, _)| start))
+                    .unwrap_or(data.len());
+                data.drain(..cut);
+                ranges.iter_mut().flatten().for_each(|(start, end)| {
+                    *start -= cut;
+                    *end -= cut;
+                });
+            }
         }
     }
 }
 
+/// Decoded rows of a query, stored column by column
+#[derive(Clone, Debug, Default)]
+pub struct RecordBatch {
+    pub(crate) columns: Arc<Vec<String>>,
+    pub(crate) timestamps: Vec<i64>,
+    pub(crate) values: Vec<ColumnValues>,
+    /// First row which is not consumed by the iterator yet
+    pub(crate) offset: usize,
+    pub(crate) precision: TimestampPrecision,
+}
+
 impl RecordBatch {
-    fn new(columns: Vec<String>, values: Vec<ValueRow>) -> Self {
+    fn new(columns: Vec<String>, data_types: &[DataType], precision: TimestampPrecision) -> Self {
+        let values = data_types
+            .iter()
+            .map(|data_type| ColumnValues::new(*data_type))
+            .collect();
         Self {
-            columns,
+            columns: Arc::new(columns),
+            timestamps: vec![],
             values,
-            is_empty: false,
+            offset: 0,
+            precision,
+        }
+    }
+
+    /// Number of rows which are not consumed yet
+    fn len(&self) -> usize {
+        self.timestamps.len() - self.offset
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop all rows, the columns are kept
+    fn clear(&mut self) {
+        self.timestamps.clear();
+        self.values.iter_mut().for_each(ColumnValues::clear);
+        self.offset = 0;
+    }
+
+    /// Decode one page and append its rows, `column_indexes` maps every column to its buffer.
+    ///
+    /// The batch is left untouched if the page can't be decoded.
+    fn append(
+        &mut self,
+        query_data_set: &TSQueryDataSet,
+        column_indexes: &[usize],
+    ) -> Result<(), IotDBError> {
+        let rows = self.timestamps.len();
+        let result = self.decode(query_data_set, column_indexes);
+        if result.is_err() {
+            self.timestamps.truncate(rows);
+            self.values
+                .iter_mut()
+                .for_each(|values| values.truncate(rows));
+        }
+        result
+    }
+
+    fn decode(
+        &mut self,
+        query_data_set: &TSQueryDataSet,
+        column_indexes: &[usize],
+    ) -> Result<(), IotDBError> {
+        let time = &query_data_set.time;
+        let rows = read_timestamps(time, &mut self.timestamps)?;
+
+        for ((name, values), index) in self
+            .columns
+            .iter()
+            .zip(&mut self.values)
+            .zip(column_indexes)
+        {
+            let (buffer, bitmap) = match (
+                query_data_set.value_list.get(*index),
+                query_data_set.bitmap_list.get(*index),
+            ) {
+                (Some(buffer), Some(bitmap)) => (buffer, bitmap),
+                _ => {
+                    return Err(IotDBError::Decode(format!(
+                        "column '{}' is missing in the data set",
+                        name
+                    )))
+                }
+            };
+            values
+                .decode(buffer, bitmap, rows)
+                .map_err(|error| match error {
+                    IotDBError::Decode(reason) => {
+                        IotDBError::Decode(format!("column '{}', {}", name, reason))
+                    }
+                    error => error,
+                })?;
         }
+        Ok(())
+    }
+
+    fn column_index(&self, name: &str) -> Result<usize, IotDBError> {
+        self.columns
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| IotDBError::ColumnNotFound(name.to_string()))
+    }
+
+    /// Value of the column at `index` converted into `T`
+    fn get<T: FromValue>(&self, index: usize, row: usize) -> Result<T, IotDBError> {
+        T::from_value(&self.values[index].value(row)).map_err(|error| match error {
+            IotDBError::Conversion(reason) => {
+                IotDBError::Conversion(format!("column '{}', {}", self.columns[index], reason))
+            }
+            error => error,
+        })
+    }
+
+    fn row(&self, row: usize) -> ValueRow {
+        let mut value_row = ValueRow::new();
+        value_row.set_timestamp(self.timestamps[row]);
+        value_row.precision = self.precision;
+        value_row.columns = Arc::clone(&self.columns);
+        self.values.iter().for_each(|values| {
+            value_row.add_field(values.field(row));
+        });
+        value_row
+    }
+
+    /// Iterate the rows which are not consumed yet
+    #[cfg(test)]
+    fn rows(&self) -> impl Iterator<Item = ValueRow> + '_ {
+        (self.offset..self.timestamps.len()).map(|row| self.row(row))
+    }
+
+    /// Drop the rows which are consumed already
+    #[cfg(feature = "parquet")]
+    fn compact(&mut self) {
+        self.timestamps.drain(..self.offset);
+        let offset = self.offset;
+        self.values
+            .iter_mut()
+            .for_each(|values| values.drain(offset));
+        self.offset = 0;
+    }
+
+    /// Build a DataFrame of the next `rows` unconsumed rows, with a `Time` column
+    /// in `time_zone` unless it's `None`
+    fn to_df(&self, rows: usize, time_zone: Option<&str>) -> Result<DataFrame, IotDBError> {
+        let rows = self.offset..self.offset + rows;
+        let mut columns: Vec<Series> = Vec::with_capacity(self.columns.len() + 1);
+        if let Some(time_zone) = time_zone {
+            let timestamps: Vec<Option<i64>> = self.timestamps[rows.clone()]
+                .iter()
+                .map(|timestamp| Some(*timestamp))
+                .collect();
+            columns.push(time_series("Time", &timestamps, self.precision, time_zone));
+        }
+        for (name, values) in self.columns.iter().zip(&self.values) {
+            columns.push(values.to_series(name, rows.clone()));
+        }
+        Ok(DataFrame::new(columns)?)
     }
 }
 
-#[derive(Clone, Debug, Default)]
+/// Decode one aligned page of raw buffers and return its number of rows, only built with
+/// the `bench` feature for the decode benchmark
+#[cfg(feature = "bench")]
+pub fn decode_page(
+    data_types: &[DataType],
+    time: Vec<u8>,
+    value_list: Vec<Vec<u8>>,
+    bitmap_list: Vec<Vec<u8>>,
+) -> Result<usize, IotDBError> {
+    let columns = (0..data_types.len()).map(|index| format!("s{}", index));
+    let column_indexes: Vec<usize> = (0..data_types.len()).collect();
+    let mut batch = RecordBatch::new(columns.collect(), data_types, TimestampPrecision::default());
+    batch.append(
+        &TSQueryDataSet::new(time, value_list, bitmap_list),
+        &column_indexes,
+    )?;
+    Ok(batch.len())
+}
+
+/// Server side cursor of a query, used to fetch the remaining results page by page
+pub(crate) struct QueryCursor {
+    client: Rc<RefCell<ClientType>>,
+    session_id: i64,
+    statement_id: i64,
+    query_id: i64,
+    statement: String,
+    fetch_size: i32,
+    timeout: i64,
+    is_align: bool,
+    is_closed: bool,
+    cancel_token: CancelToken,
+}
+
+impl fmt::Debug for QueryCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryCursor")
+            .field("session_id", &self.session_id)
+            .field("statement_id", &self.statement_id)
+            .field("query_id", &self.query_id)
+            .field("statement", &self.statement)
+            .field("fetch_size", &self.fetch_size)
+            .field("timeout", &self.timeout)
+            .field("is_align", &self.is_align)
+            .field("is_closed", &self.is_closed)
+            .field("is_cancelled", &self.cancel_token.is_cancelled())
+            .finish()
+    }
+}
+
+impl QueryCursor {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        client: Rc<RefCell<ClientType>>,
+        session_id: i64,
+        statement_id: i64,
+        query_id: i64,
+        statement: &str,
+        fetch_size: i32,
+        timeout: i64,
+        cancel_token: CancelToken,
+    ) -> Self {
+        Self {
+            client,
+            session_id,
+            statement_id,
+            query_id,
+            statement: statement.to_string(),
+            fetch_size,
+            timeout,
+            is_align: true,
+            is_closed: false,
+            cancel_token,
+        }
+    }
+
+    fn handle(&self) -> Option<QueryHandle> {
+        match self.is_closed || self.cancel_token.is_cancelled() {
+            true => None,
+            false => Some(QueryHandle::new(self.cancel_token.clone())),
+        }
+    }
+
+    /// Fetch the next page of the query, return `None` once the server has no more results
+    fn fetch(&mut self) -> anyhow::Result<Option<TSFetchResultsResp>> {
+        if self.is_closed {
+            return Ok(None);
+        }
+        if self.cancel_token.is_cancelled() {
+            self.is_closed = true;
+            bail!("Query {} was cancelled", self.query_id)
+        }
+
+        let req = TSFetchResultsReq::new(
+            self.session_id,
+            self.statement.clone(),
+            self.fetch_size,
+            self.query_id,
+            self.is_align,
+            self.timeout,
+        );
+        let resp = self.client.borrow_mut().fetch_results(req)?;
+        if resp.status.code != SUCCESS_CODE {
+            let msg = resp.status.message.unwrap_or_else(|| "None".to_string());
+            error!("Fetch results of query {} failed, {}", self.query_id, msg);
+            bail!(msg)
+        }
+
+        debug!(
+            "Fetch results of query {}, has result set: {}",
+            self.query_id, resp.has_result_set
+        );
+        if resp.has_result_set {
+            Ok(Some(resp))
+        } else {
+            self.close()?;
+            Ok(None)
+        }
+    }
+
+    /// Release the query resources on the server
+    fn close(&mut self) -> anyhow::Result<()> {
+        // a cancelled query is closed by its cancel token
+        if self.is_closed || self.cancel_token.is_cancelled() {
+            self.is_closed = true;
+            return Ok(());
+        }
+
+        self.is_closed = true;
+        let req = TSCloseOperationReq::new(self.session_id, self.query_id, self.statement_id);
+        let status = self.client.borrow_mut().close_operation(req)?;
+        if status.code == SUCCESS_CODE {
+            debug!("Close operation of query {}", self.query_id);
+            Ok(())
+        } else {
+            bail!(status.message.unwrap_or_else(|| "None".to_string()))
+        }
+    }
+}
+
+impl Drop for QueryCursor {
+    fn drop(&mut self) {
+        if let Err(error) = self.close() {
+            debug!(
+                "Close operation of query {} failed, {}",
+                self.query_id, error
+            );
+        }
+    }
+}
+
+/// Query result, rows are fetched lazily from the server page by page while iterating
+#[derive(Debug, Default)]
 pub struct DataSet {
+    data_types: Vec<DataType>,
+    column_name_index_map: Option<BTreeMap<String, i32>>,
+    column_indexes: Vec<usize>,
     record_batch: RecordBatch,
     ignore_time_stamp: Option<bool>,
+    time_zone: SessionTimeZone,
+    time_format: Option<String>,
+    precision: TimestampPrecision,
+    schema: Schema,
+    tracing_info: Option<TracingInfo>,
+    cursor: Option<QueryCursor>,
+    non_align: Option<NonAlignDataSet>,
 }
 
 impl DataSet {
-    pub(crate) fn new(resp: TSExecuteStatementResp) -> DataSet {
+    pub(crate) fn new(
+        resp: TSExecuteStatementResp,
+        cursor: Option<QueryCursor>,
+        time_zone: SessionTimeZone,
+        time_format: Option<&str>,
+        precision: TimestampPrecision,
+    ) -> anyhow::Result<DataSet> {
         debug!("{:#?}", resp);
         // set data_types
         let data_types: Vec<DataType> = match resp.data_type_list.clone() {
@@ -107,175 +732,1809 @@ impl DataSet {
             }
         };
 
-        let record_batch = if resp.query_data_set.is_some() {
-            Self::resp_to_rows(resp.clone(), data_types)
-        } else {
-            RecordBatch::default()
+        let columns = resp.columns.unwrap_or_default();
+        let column_indexes = column_indexes(&columns, &resp.column_name_index_map);
+        let schema = Schema::new(
+            resp.operation_type,
+            &columns,
+            &data_types,
+            &column_indexes,
+            resp.sg_columns,
+            resp.alias_columns,
+        );
+        let mut data_set = Self {
+            record_batch: RecordBatch::new(columns.clone(), &data_types, precision),
+            data_types,
+            column_name_index_map: resp.column_name_index_map,
+            column_indexes,
+            ignore_time_stamp: resp.ignore_time_stamp,
+            time_zone,
+            time_format: time_format.map(str::to_string),
+            precision,
+            schema,
+            tracing_info: resp.tracing_info.map(TracingInfo::from),
+            cursor: None,
+            non_align: None,
         };
 
-        Self {
-            record_batch,
-            ignore_time_stamp: resp.ignore_time_stamp,
+        if let Some(query_data_set) = resp.query_data_set {
+            data_set
+                .record_batch
+                .append(&query_data_set, &data_set.column_indexes)?;
+            data_set.cursor = cursor;
+        } else if let Some(non_align_data_set) = resp.non_align_query_data_set {
+            let cursor = cursor.map(|mut cursor| {
+                cursor.is_align = false;
+                cursor
+            });
+            let mut non_align = NonAlignDataSet::new(
+                &columns,
+                &data_set.data_types,
+                data_set.column_name_index_map.clone(),
+                time_zone,
+                data_set.time_format.clone(),
+                precision,
+                cursor,
+            );
+            non_align.append(non_align_data_set)?;
+            data_set.non_align = Some(non_align);
+        } else {
+            data_set.cursor = cursor;
+        }
+
+        Ok(data_set)
+    }
+
+    /// Fetch the next non-empty page from the server, return false once all results are fetched
+    fn fetch_next(&mut self) -> anyhow::Result<bool> {
+        while let Some(cursor) = self.cursor.as_mut() {
+            match cursor.fetch()? {
+                None => {
+                    self.cursor = None;
+                }
+                Some(TSFetchResultsResp {
+                    query_data_set: Some(query_data_set),
+                    ..
+                }) => {
+                    let rows = self.record_batch.len();
+                    self.record_batch
+                        .append(&query_data_set, &self.column_indexes)?;
+                    if self.record_batch.len() > rows {
+                        return Ok(true);
+                    }
+                }
+                Some(_) => bail!("Query data set is missing in the fetched page"),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Fetch all remaining pages from the server into memory
+    pub fn fetch_all(&mut self) -> anyhow::Result<()> {
+        match self.non_align.as_mut() {
+            None => while self.fetch_next()? {},
+            Some(non_align) => non_align.fetch_all()?,
+        }
+        Ok(())
+    }
+
+    /// Close the query on the server, rows that are already fetched are kept
+    pub fn close(&mut self) -> anyhow::Result<()> {
+        if let Some(non_align) = self.non_align.as_mut() {
+            return non_align.close();
+        }
+        match self.cursor.take() {
+            None => Ok(()),
+            Some(mut cursor) => cursor.close(),
         }
     }
 
-    fn resp_to_rows(resp: TSExecuteStatementResp, data_types: Vec<DataType>) -> RecordBatch {
-        const FLAG: i32 = 0x80;
-        let query_data_set = resp.query_data_set.unwrap();
-        let columns = resp.columns.unwrap();
-        let bitmap_buffer = query_data_set.bitmap_list;
-        let mut value_list = query_data_set.value_list;
+    /// Handle to cancel the query from another thread, `None` once all pages are fetched
+    pub fn query_handle(&self) -> Option<QueryHandle> {
+        match self.non_align.as_ref() {
+            None => self.cursor.as_ref().and_then(QueryCursor::handle),
+            Some(non_align) => non_align.query_handle(),
+        }
+    }
 
-        let mut values: Vec<ValueRow> = Vec::new();
-        let mut row_num = 0;
+    /// Precision of the raw timestamps of the rows
+    pub fn timestamp_precision(&self) -> TimestampPrecision {
+        self.precision
+    }
+
+    /// Time zone of the displayed and exported timestamps, the session time zone by default
+    pub fn time_zone(&self) -> SessionTimeZone {
+        self.time_zone
+    }
+
+    pub fn set_time_zone(&mut self, time_zone: SessionTimeZone) {
+        self.time_zone = time_zone;
+        if let Some(non_align) = self.non_align.as_mut() {
+            non_align.time_zone = time_zone;
+        }
+    }
+
+    /// Display timestamps with a `strftime` pattern, e.g. `%Y-%m-%d %H:%M:%S%.3f`,
+    /// `None` restores the default format
+    pub fn set_time_format(&mut self, time_format: Option<&str>) -> anyhow::Result<()> {
+        if let Some(time_format) = time_format.filter(|format| !is_valid_time_format(format)) {
+            bail!("Invalid time format '{}'", time_format);
+        }
+        self.time_format = time_format.map(str::to_string);
+        if let Some(non_align) = self.non_align.as_mut() {
+            non_align.time_format = self.time_format.clone();
+        }
+        Ok(())
+    }
+
+    /// Column names, types and storage groups of the result
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Tracing info of a `TRACING` query, `None` for other statements
+    pub fn tracing_info(&self) -> Option<&TracingInfo> {
+        self.tracing_info.as_ref()
+    }
+
+    /// Whether the query result is aligned by time, `DISABLE ALIGN` queries are not
+    pub fn is_align(&self) -> bool {
+        self.non_align.is_none()
+    }
+
+    /// Take the non-aligned result of a `DISABLE ALIGN` query, aligned results return `None`
+    pub fn into_non_align(mut self) -> Option<NonAlignDataSet> {
+        self.non_align.take()
+    }
+
+    /// Fetch all remaining pages and return the timestamps and the values of one column,
+    /// e.g. `data_set.column::<f64>("root.sg.d1.s1")`, rows are not consumed.
+    ///
+    /// The timestamps are shared by all columns and null values are `None`.
+    pub fn column<T: FromValue>(&mut self, name: &str) -> anyhow::Result<(&[i64], Vec<Option<T>>)> {
+        if !self.is_align() {
+            bail!("Non-aligned results have no shared timestamps, see DataSet::into_non_align");
+        }
+        let index = self.record_batch.column_index(name)?;
+        self.fetch_all()?;
+
+        let batch = &self.record_batch;
+        let values = (batch.offset..batch.timestamps.len())
+            .map(|row| batch.get(index, row))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((&batch.timestamps[batch.offset..], values))
+    }
+
+    /// Iterate the `(timestamp, value)` points of one column, rows where the column is null
+    /// are skipped. Pages are fetched while iterating, so only one page is kept in memory.
+    pub fn points<T: FromValue>(self, name: &str) -> anyhow::Result<Points<T>> {
+        if !self.is_align() {
+            bail!("Points of non-aligned results are read with DataSet::into_non_align");
+        }
+        let column = self.record_batch.column_index(name)?;
+        Ok(Points {
+            data_set: self,
+            column,
+            marker: PhantomData,
+        })
+    }
+
+    /// Fetch all remaining pages and compute the statistics of every column, rows are not consumed
+    pub fn stats(&mut self) -> anyhow::Result<Vec<ColumnStats>> {
+        if !self.is_align() {
+            bail!("Statistics of non-aligned results are not supported");
+        }
+        self.fetch_all()?;
+        let batch = &self.record_batch;
+        Ok(batch
+            .columns
+            .iter()
+            .zip(&batch.values)
+            .map(|(name, values)| {
+                ColumnStats::new(
+                    name,
+                    values,
+                    &batch.timestamps,
+                    batch.offset,
+                    batch.precision,
+                )
+            })
+            .collect())
+    }
+
+    /// Fetch all remaining pages and check the timestamps for gaps longer than `max_interval`,
+    /// duplicates and rows out of order, rows are not consumed
+    pub fn check_time(&mut self, max_interval: Duration) -> anyhow::Result<TimeReport> {
+        if !self.is_align() {
+            bail!("Time checks of non-aligned results are not supported");
+        }
+        self.fetch_all()?;
+        let batch = &self.record_batch;
+        Ok(TimeReport::check(
+            &batch.timestamps[batch.offset..],
+            batch.precision,
+            max_interval,
+        ))
+    }
+
+    /// Convert all rows of the query into a polars DataFrame.
+    ///
+    /// The timestamps become a `Time` column of milliseconds in the session time zone,
+    /// unless the server marks the result with `ignore_time_stamp`, null values stay null.
+    pub fn to_df(&mut self) -> anyhow::Result<DataFrame> {
+        if let Some(non_align) = self.non_align.as_mut() {
+            return non_align.to_df();
+        }
+        self.fetch_all()?;
+        Ok(self
+            .record_batch
+            .to_df(self.record_batch.len(), self.df_time_zone().as_deref())?)
+    }
+
+    /// Time zone of the `Time` column of DataFrames, `None` if the result has no timestamps
+    fn df_time_zone(&self) -> Option<String> {
+        match self.ignore_time_stamp.unwrap_or(false) {
+            true => None,
+            false => Some(self.time_zone.to_string()),
+        }
+    }
+
+    /// Write the remaining rows as CSV, pages are written as they are fetched from the server.
+    ///
+    /// The rows are consumed, non-aligned results are not supported.
+    pub fn write_csv<W: io::Write>(
+        &mut self,
+        writer: W,
+        options: &CsvOptions,
+    ) -> anyhow::Result<()> {
+        if self.non_align.is_some() {
+            bail!("CSV export of a non-aligned data set is not supported");
+        }
+        let with_time = !self.ignore_time_stamp.unwrap_or(false);
+        let mut csv = CsvWriter::new(
+            writer,
+            options,
+            self.time_zone,
+            &self.record_batch.columns,
+            with_time,
+        )?;
         loop {
-            let sum_len: usize = value_list.iter().map(|value| value.len()).sum();
-            if sum_len == 0 {
+            csv.write_batch(&self.record_batch)?;
+            self.record_batch.clear();
+            if !self.fetch_next()? {
                 break;
             }
+        }
+        Ok(csv.flush()?)
+    }
 
-            // construct time field
-            let mut value_row: ValueRow = ValueRow::new();
-            let mut time = query_data_set.time.clone();
-            if !time.is_empty() {
-                let mut time_buffer = Cursor::new(time.drain(..8).collect::<Vec<u8>>());
-                value_row.timestamp(time_buffer.read_i64::<BigEndian>().unwrap());
+    /// Write the remaining rows as a JSON array or newline-delimited JSON objects, pages are
+    /// written as they are fetched from the server.
+    ///
+    /// The rows are consumed, non-aligned results are not supported.
+    pub fn write_json<W: io::Write>(
+        &mut self,
+        writer: W,
+        options: &JsonOptions,
+    ) -> anyhow::Result<()> {
+        if self.non_align.is_some() {
+            bail!("JSON export of a non-aligned data set is not supported");
+        }
+        let with_time = !self.ignore_time_stamp.unwrap_or(false);
+        let mut json = JsonWriter::new(
+            writer,
+            options,
+            self.time_zone,
+            &self.record_batch.columns,
+            with_time,
+        )?;
+        loop {
+            json.write_batch(&self.record_batch)?;
+            self.record_batch.clear();
+            if !self.fetch_next()? {
+                break;
             }
+        }
+        Ok(json.finish()?)
+    }
 
-            // construct value field
-            for col_index in 0..columns.len() {
-                // add a new field
-                let column_name = columns[col_index].clone();
-                let data_type = data_types[col_index];
-                let mut field = Field::new(data_type);
-
-                // reset column name index
-                let col_index = match resp.column_name_index_map.clone() {
-                    None => col_index,
-                    Some(column_name_index_map) => *column_name_index_map
-                        .get(column_name.as_str())
-                        .unwrap_or(&(col_index as i32))
-                        as usize,
-                };
-
-                // is NaN value
-                let bitmap = bitmap_buffer[col_index][0] as i32;
-                let is_null = ((FLAG >> (row_num % 8)) & (bitmap & 0xFF)) == 0;
-
-                if !is_null {
-                    match data_type {
-                        DataType::BOOLEAN => {
-                            field.bool_value = Some(value_list[col_index][0].eq(&1));
-                            value_list[col_index].remove(0);
-                        }
-                        DataType::INT32 => {
-                            let mut buffer =
-                                Cursor::new(value_list[col_index].drain(..4).collect::<Vec<u8>>());
-                            field.int_value = Some(buffer.read_i32::<BigEndian>().unwrap());
-                        }
-                        DataType::INT64 => {
-                            let mut buffer =
-                                Cursor::new(value_list[col_index].drain(..8).collect::<Vec<u8>>());
-                            field.long_value = Some(buffer.read_i64::<BigEndian>().unwrap());
-                        }
-                        DataType::FLOAT => {
-                            let mut buffer =
-                                Cursor::new(value_list[col_index].drain(..4).collect::<Vec<u8>>());
-                            field.float_value = Some(buffer.read_f32::<BigEndian>().unwrap());
-                        }
-                        DataType::DOUBLE => {
-                            let mut buffer =
-                                Cursor::new(value_list[col_index].drain(..8).collect::<Vec<u8>>());
-                            field.double_value = Some(buffer.read_f64::<BigEndian>().unwrap());
-                        }
-                        DataType::TEXT => {
-                            let mut length_buffer =
-                                Cursor::new(value_list[col_index].drain(..4).collect::<Vec<u8>>());
-                            let length = length_buffer.read_i32::<BigEndian>().unwrap() as usize;
-                            let binary = value_list[col_index].drain(..length).collect::<Vec<u8>>();
-                            field.binary_value = Some(binary);
-                        }
-                    }
+    /// Fetch all remaining pages and print them as a table to stdout
+    pub fn show(&mut self) {
+        if let Err(error) = self.fetch_all() {
+            error!("{}", error);
+        }
+        if let Err(error) = self.render(&mut io::stdout(), &TableOptions::default()) {
+            error!("{}", error);
+        }
+    }
+
+    /// Render the rows fetched so far as a table into `writer`, the rows are not consumed.
+    ///
+    /// Call [`DataSet::fetch_all`] first to render the whole result.
+    pub fn render<W: io::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &TableOptions,
+    ) -> anyhow::Result<()> {
+        self.table(options).print(writer)?;
+        Ok(())
+    }
+
+    fn table(&self, options: &TableOptions) -> Table {
+        if let Some(non_align) = self.non_align.as_ref() {
+            return non_align.table(options);
+        }
+        TableRenderer {
+            options,
+            time_zone: &self.time_zone,
+            time_format: self.time_format.as_deref(),
+        }
+        .batch_table(&self.record_batch, !self.ignore_time_stamp.unwrap_or(false))
+    }
+}
+
+/// The rows fetched so far as a table, see [`DataSet::render`]
+impl fmt::Display for DataSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.table(&TableOptions::default()))
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl DataSet {
+    /// Convert all rows of the query into an Arrow record batch.
+    ///
+    /// The timestamps become a `Time` column of `Timestamp` in the server precision, i.e.
+    /// `Millisecond`, `Microsecond` or `Nanosecond`, unless the server marks the result with
+    /// `ignore_time_stamp`. Nulls come from the IoTDB bitmaps.
+    pub fn to_arrow(&mut self) -> anyhow::Result<arrow::record_batch::RecordBatch> {
+        if let Some(non_align) = self.non_align.as_mut() {
+            return non_align.to_arrow();
+        }
+        self.fetch_all()?;
+        Ok(crate::arrow_batch::batch_to_arrow(
+            &self.record_batch,
+            !self.ignore_time_stamp.unwrap_or(false),
+        )?)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl DataSet {
+    /// Write the remaining rows into a Parquet file, laid out like [`DataSet::to_df`].
+    ///
+    /// Pages are fetched from the server until a row group is full, so large results are
+    /// exported without holding them in memory. The rows are consumed, non-aligned results
+    /// are not supported.
+    pub fn write_parquet<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &ParquetOptions,
+    ) -> anyhow::Result<()> {
+        if self.non_align.is_some() {
+            bail!("Parquet export of a non-aligned data set is not supported");
+        }
+        if options.row_group_size == 0 {
+            bail!("Parquet row group size must be positive");
+        }
+        let mut writer = io::BufWriter::new(File::create(path)?);
+
+        let first = match self.next_frame(options.row_group_size)? {
+            Some(frame) => frame,
+            None => self.record_batch.to_df(0, self.df_time_zone().as_deref())?,
+        };
+        let frames = std::iter::from_fn(|| {
+            self.next_frame(options.row_group_size)
+                .map_err(|error| match error.downcast::<IotDBError>() {
+                    Ok(error) => error,
+                    Err(error) => IotDBError::IO(io::Error::other(error.to_string())),
+                })
+                .transpose()
+        });
+        crate::parquet::write_frames(&mut writer, first, frames, options)?;
+        Ok(io::Write::flush(&mut writer)?)
+    }
+
+    /// Fetch pages until `rows` rows are buffered and consume them into a DataFrame
+    fn next_frame(&mut self, rows: usize) -> anyhow::Result<Option<DataFrame>> {
+        while self.record_batch.len() < rows && self.fetch_next()? {}
+        if self.record_batch.is_empty() {
+            return Ok(None);
+        }
+
+        let rows = rows.min(self.record_batch.len());
+        let frame = self
+            .record_batch
+            .to_df(rows, self.df_time_zone().as_deref())?;
+        self.record_batch.offset += rows;
+        self.record_batch.compact();
+        Ok(Some(frame))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl DataSet {
+    /// Iterate the remaining rows deserialized into `T`, see [`ValueRow::deserialize`]
+    pub fn deserialize_rows<T: serde::de::DeserializeOwned>(
+        &mut self,
+    ) -> impl Iterator<Item = anyhow::Result<T>> + '_ {
+        self.map(|row| Ok(row?.deserialize()?))
+    }
+}
+
+/// Iterate the aligned rows, non-aligned results have no rows, see [`DataSet::into_non_align`]
+impl Iterator for DataSet {
+    type Item = anyhow::Result<ValueRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.record_batch.is_empty() {
+            self.record_batch.clear();
+            if let Err(error) = self.fetch_next() {
+                return Some(Err(error));
+            }
+        }
+        if self.record_batch.is_empty() {
+            return None;
+        }
+
+        let row = self.record_batch.row(self.record_batch.offset);
+        self.record_batch.offset += 1;
+        Some(Ok(row))
+    }
+}
+
+/// Points of one column of an aligned query result, see [`DataSet::points`]
+#[derive(Debug)]
+pub struct Points<T> {
+    data_set: DataSet,
+    column: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: FromValue> Iterator for Points<T> {
+    type Item = anyhow::Result<(i64, T)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.data_set.record_batch.is_empty() {
+                self.data_set.record_batch.clear();
+                match self.data_set.fetch_next() {
+                    Err(error) => return Some(Err(error)),
+                    Ok(false) => return None,
+                    Ok(true) => {}
+                }
+            }
+
+            let batch = &mut self.data_set.record_batch;
+            let row = batch.offset;
+            batch.offset += 1;
+            if batch.values[self.column].is_null(row) {
+                continue;
+            }
+            return Some(
+                batch
+                    .get(self.column, row)
+                    .map(|value| (batch.timestamps[row], value))
+                    .map_err(Into::into),
+            );
+        }
+    }
+}
+
+/// One column of a non-aligned query result, every value is paired with its own timestamp
+#[derive(Clone, Debug)]
+pub struct NonAlignColumn {
+    name: String,
+    data_type: DataType,
+    timestamps: Vec<i64>,
+    fields: Vec<Field>,
+}
+
+impl NonAlignColumn {
+    fn new(name: &str, data_type: DataType) -> Self {
+        Self {
+            name: name.to_string(),
+            data_type,
+            timestamps: vec![],
+            fields: vec![],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn data_type(&self) -> DataType {
+        self.data_type
+    }
+
+    pub fn timestamps(&self) -> &[i64] {
+        &self.timestamps
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Iterate the `(timestamp, field)` points of the column
+    pub fn iter(&self) -> impl Iterator<Item = (i64, &Field)> {
+        self.timestamps.iter().copied().zip(self.fields.iter())
+    }
+}
+
+/// Result of a `DISABLE ALIGN` query, each column carries its own time list.
+///
+/// Iterating yields the column segments page by page as they are fetched from the server,
+/// `show` and `to_df` fetch all remaining pages first.
+#[derive(Debug, Default)]
+pub struct NonAlignDataSet {
+    columns: Vec<NonAlignColumn>,
+    column_indexes: Vec<usize>,
+    time_zone: SessionTimeZone,
+    time_format: Option<String>,
+    precision: TimestampPrecision,
+    cursor: Option<QueryCursor>,
+}
+
+impl NonAlignDataSet {
+    fn new(
+        columns: &[String],
+        data_types: &[DataType],
+        column_name_index_map: Option<BTreeMap<String, i32>>,
+        time_zone: SessionTimeZone,
+        time_format: Option<String>,
+        precision: TimestampPrecision,
+        cursor: Option<QueryCursor>,
+    ) -> Self {
+        let column_indexes = column_indexes(columns, &column_name_index_map);
+
+        Self {
+            columns: columns
+                .iter()
+                .zip(data_types)
+                .map(|(name, data_type)| NonAlignColumn::new(name, *data_type))
+                .collect(),
+            column_indexes,
+            time_zone,
+            time_format,
+            precision,
+            cursor,
+        }
+    }
+
+    pub fn columns(&self) -> &[NonAlignColumn] {
+        &self.columns
+    }
+
+    /// Handle to cancel the query from another thread, `None` once all pages are fetched
+    pub fn query_handle(&self) -> Option<QueryHandle> {
+        self.cursor.as_ref().and_then(QueryCursor::handle)
+    }
+
+    pub fn column(&self, name: &str) -> Option<&NonAlignColumn> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Decode one page and append its points to the columns
+    fn append(&mut self, data_set: TSQueryNonAlignDataSet) -> Result<(), IotDBError> {
+        // decode every column first, so a broken page leaves the columns untouched
+        let mut points: Vec<(Vec<i64>, Vec<Field>)> = Vec::with_capacity(self.columns.len());
+        for (column, index) in self.columns.iter().zip(&self.column_indexes) {
+            let (time, value) = match (
+                data_set.time_list.get(*index),
+                data_set.value_list.get(*index),
+            ) {
+                (Some(time), Some(value)) => (time, value),
+                _ => {
+                    return Err(IotDBError::Decode(format!(
+                        "column '{}' is missing in the non-aligned data set",
+                        column.name
+                    )))
                 }
-                value_row.add_field(field);
+            };
+
+            let mut timestamps = vec![];
+            let rows = read_timestamps(time, &mut timestamps)?;
+            let mut fields = Vec::with_capacity(rows);
+            let mut pos = 0;
+            for _ in 0..rows {
+                let field = read_field(column.data_type, value, &mut pos).map_err(|error| {
+                    IotDBError::Decode(format!("column '{}', {}", column.name, error))
+                })?;
+                fields.push(field);
             }
-            row_num += 1;
-            values.push(value_row);
+            points.push((timestamps, fields));
+        }
+
+        for (column, (timestamps, fields)) in self.columns.iter_mut().zip(points) {
+            column.timestamps.extend(timestamps);
+            column.fields.extend(fields);
         }
+        Ok(())
+    }
 
-        RecordBatch::new(columns, values)
+    /// Fetch the next page from the server, return false once all results are fetched
+    fn fetch_next(&mut self) -> anyhow::Result<bool> {
+        let cursor = match self.cursor.as_mut() {
+            None => return Ok(false),
+            Some(cursor) => cursor,
+        };
+        match cursor.fetch()? {
+            None => {
+                self.cursor = None;
+                Ok(false)
+            }
+            Some(TSFetchResultsResp {
+                non_align_query_data_set: Some(data_set),
+                ..
+            }) => {
+                self.append(data_set)?;
+                Ok(true)
+            }
+            Some(_) => bail!("Non-aligned data set is missing in the fetched page"),
+        }
     }
 
-    pub fn to_df(&self) -> Result<DataFrame> {
-        let columns: Vec<Series> = Vec::new();
+    /// Fetch all remaining pages from the server into memory
+    pub fn fetch_all(&mut self) -> anyhow::Result<()> {
+        while self.fetch_next()? {}
+        Ok(())
+    }
 
-        DataFrame::new(columns)
+    /// Close the query on the server, points that are already fetched are kept
+    pub fn close(&mut self) -> anyhow::Result<()> {
+        match self.cursor.take() {
+            None => Ok(()),
+            Some(mut cursor) => cursor.close(),
+        }
     }
 
+    /// Convert all points into a polars DataFrame.
+    ///
+    /// Every column is paired with a `Time(<column>)` datetime column, shorter columns are
+    /// padded with nulls to the length of the longest one.
+    pub fn to_df(&mut self) -> anyhow::Result<DataFrame> {
+        self.fetch_all()?;
+        let height = self.columns.iter().map(|column| column.len()).max();
+
+        let mut series: Vec<Series> = Vec::with_capacity(self.columns.len() * 2);
+        for column in &self.columns {
+            let padding = height.unwrap_or(0) - column.len();
+            let timestamps: Vec<Option<i64>> = column
+                .timestamps
+                .iter()
+                .map(|timestamp| Some(*timestamp))
+                .chain(std::iter::repeat_n(None, padding))
+                .collect();
+            let null = Field::new(column.data_type);
+            let fields = column
+                .fields
+                .iter()
+                .chain(std::iter::repeat_n(&null, padding));
+
+            series.push(time_series(
+                &format!("Time({})", column.name),
+                &timestamps,
+                self.precision,
+                &self.time_zone.to_string(),
+            ));
+            series.push(fields_to_series(&column.name, column.data_type, fields));
+        }
+
+        Ok(DataFrame::new(series)?)
+    }
+
+    /// Convert all points into an Arrow record batch, laid out like [`NonAlignDataSet::to_df`]
+    #[cfg(feature = "arrow")]
+    pub fn to_arrow(&mut self) -> anyhow::Result<arrow::record_batch::RecordBatch> {
+        self.fetch_all()?;
+        Ok(crate::arrow_batch::non_align_to_arrow(
+            &self.columns,
+            self.precision,
+        )?)
+    }
+
+    /// Fetch all remaining pages and print them as a table to stdout
     pub fn show(&mut self) {
-        let mut batch = self.record_batch.clone();
-        debug!("{:#?}", &batch);
+        if let Err(error) = self.fetch_all() {
+            error!("{}", error);
+        }
+        if let Err(error) = self.render(&mut io::stdout(), &TableOptions::default()) {
+            error!("{}", error);
+        }
+    }
+
+    /// Render the points fetched so far as a table into `writer`, see [`DataSet::render`]
+    pub fn render<W: io::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        options: &TableOptions,
+    ) -> anyhow::Result<()> {
+        self.table(options).print(writer)?;
+        Ok(())
+    }
+
+    fn table(&self, options: &TableOptions) -> Table {
+        TableRenderer {
+            options,
+            time_zone: &self.time_zone,
+            time_format: self.time_format.as_deref(),
+        }
+        .non_align_table(&self.columns, self.precision)
+    }
+}
+
+impl fmt::Display for NonAlignDataSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.table(&TableOptions::default()))
+    }
+}
 
-        let mut table: Table = Table::new();
-        table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        if !batch.is_empty {
-            let ignore_time_stamp = self.ignore_time_stamp.unwrap_or(false);
+impl Iterator for NonAlignDataSet {
+    type Item = anyhow::Result<NonAlignColumn>;
 
-            // add col name row
-            if !ignore_time_stamp {
-                batch.columns.insert(0, "Time".to_string());
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(index) = self.columns.iter().position(|column| !column.is_empty()) {
+                let column = &mut self.columns[index];
+                let mut segment = NonAlignColumn::new(&column.name, column.data_type);
+                segment.timestamps = std::mem::take(&mut column.timestamps);
+                segment.fields = std::mem::take(&mut column.fields);
+                return Some(Ok(segment));
             }
-            let mut col_name_cells: Vec<Cell> = Vec::new();
-            batch
-                .columns
+            match self.fetch_next() {
+                Ok(true) => continue,
+                Ok(false) => return None,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// Map every column to the index of its value buffer, by `column_name_index_map` if present
+fn column_indexes(
+    columns: &[String],
+    column_name_index_map: &Option<BTreeMap<String, i32>>,
+) -> Vec<usize> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(index, name)| match column_name_index_map {
+            None => index,
+            Some(column_name_index_map) => *column_name_index_map
+                .get(name.as_str())
+                .unwrap_or(&(index as i32)) as usize,
+        })
+        .collect()
+}
+
+/// Whether the value of `row` is null, one bit per row and the highest bit first
+fn is_null(bitmap: &[u8], row: usize) -> bool {
+    bitmap[row / 8] & (0x80 >> (row % 8)) == 0
+}
+
+/// Take `len` bytes of the buffer at `pos` and advance it
+fn read_bytes<'a>(buffer: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], IotDBError> {
+    match buffer.get(*pos..*pos + len) {
+        Some(bytes) => {
+            *pos += len;
+            Ok(bytes)
+        }
+        None => Err(IotDBError::Decode(format!(
+            "need {} bytes at offset {}, but the buffer has {} bytes",
+            len,
+            pos,
+            buffer.len()
+        ))),
+    }
+}
+
+fn read_array<const N: usize>(buffer: &[u8], pos: &mut usize) -> Result<[u8; N], IotDBError> {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(read_bytes(buffer, pos, N)?);
+    Ok(bytes)
+}
+
+/// Read the length prefix of a TEXT value
+fn read_length(buffer: &[u8], pos: &mut usize) -> Result<usize, IotDBError> {
+    let length = i32::from_be_bytes(read_array(buffer, pos)?);
+    usize::try_from(length).map_err(|_| {
+        IotDBError::Decode(format!("invalid text length {} at offset {}", length, pos))
+    })
+}
+
+/// Decode a time buffer into `timestamps`, return the number of rows
+fn read_timestamps(time: &[u8], timestamps: &mut Vec<i64>) -> Result<usize, IotDBError> {
+    if !time.len().is_multiple_of(8) {
+        return Err(IotDBError::Decode(format!(
+            "time buffer of {} bytes is not a multiple of 8",
+            time.len()
+        )));
+    }
+
+    let rows = time.len() / 8;
+    timestamps.reserve(rows);
+    let mut pos = 0;
+    for _ in 0..rows {
+        timestamps.push(i64::from_be_bytes(read_array(time, &mut pos)?));
+    }
+    Ok(rows)
+}
+
+/// Read one non-null value of the given type from a value buffer
+fn read_field(data_type: DataType, buffer: &[u8], pos: &mut usize) -> Result<Field, IotDBError> {
+    let mut field = Field::new(data_type);
+    match data_type {
+        DataType::BOOLEAN => field.bool_value = Some(read_array::<1>(buffer, pos)?[0] == 1),
+        DataType::INT32 => field.int_value = Some(i32::from_be_bytes(read_array(buffer, pos)?)),
+        DataType::INT64 => field.long_value = Some(i64::from_be_bytes(read_array(buffer, pos)?)),
+        DataType::FLOAT => field.float_value = Some(f32::from_be_bytes(read_array(buffer, pos)?)),
+        DataType::DOUBLE => field.double_value = Some(f64::from_be_bytes(read_array(buffer, pos)?)),
+        DataType::TEXT => {
+            let length = read_length(buffer, pos)?;
+            field.binary_value = Some(read_bytes(buffer, pos, length)?.to_vec());
+        }
+    }
+    Ok(field)
+}
+
+/// Build a polars datetime series in the given time zone, polars has no microsecond unit
+/// so microseconds are stored as nanoseconds and those which overflow become null
+fn time_series(
+    name: &str,
+    timestamps: &[Option<i64>],
+    precision: TimestampPrecision,
+    time_zone: &str,
+) -> Series {
+    let timestamps = match precision {
+        TimestampPrecision::Microsecond => Int64Chunked::new_from_opt_slice(
+            name,
+            &timestamps
                 .iter()
-                .for_each(|col_name| col_name_cells.push(cell!(col_name)));
-            table.set_titles(PrettyRow::new(col_name_cells));
-
-            // add value rows
-            batch.values.iter().for_each(|row| {
-                let mut value_cells: Vec<Cell> = Vec::new();
-                if !ignore_time_stamp {
-                    let dt: DateTime<Local> = Local.timestamp_millis(row.timestamp);
-                    value_cells.push(cell!(dt.to_string()));
-                }
+                .map(|timestamp| timestamp.and_then(|micros| micros.checked_mul(1_000)))
+                .collect::<Vec<_>>(),
+        ),
+        _ => Int64Chunked::new_from_opt_slice(name, timestamps),
+    };
+    let unit = match precision {
+        TimestampPrecision::Millisecond => TimeUnit::Milliseconds,
+        _ => TimeUnit::Nanoseconds,
+    };
+    timestamps
+        .into_datetime(unit, Some(time_zone.to_string()))
+        .into_series()
+}
 
-                row.fields.iter().for_each(|field| match field.data_type {
-                    DataType::BOOLEAN => match field.bool_value {
-                        None => value_cells.push(cell!("NaN")),
-                        Some(bool_value) => value_cells.push(cell!(bool_value)),
-                    },
-                    DataType::INT32 => match field.int_value {
-                        None => value_cells.push(cell!("NaN")),
-                        Some(int_value) => value_cells.push(cell!(int_value)),
-                    },
-                    DataType::INT64 => match field.long_value {
-                        None => value_cells.push(cell!("NaN")),
-                        Some(long_value) => value_cells.push(cell!(long_value)),
-                    },
-                    DataType::FLOAT => match field.float_value {
-                        None => value_cells.push(cell!("NaN")),
-                        Some(float_value) => value_cells.push(cell!(float_value)),
-                    },
-                    DataType::DOUBLE => match field.double_value {
-                        None => value_cells.push(cell!("NaN")),
-                        Some(double_value) => value_cells.push(cell!(double_value)),
-                    },
-                    DataType::TEXT => {
-                        match field.clone().binary_value {
-                            None => value_cells.push(cell!("NaN")),
-                            Some(binary) => {
-                                value_cells.push(cell!(String::from_utf8(binary).unwrap()))
-                            }
-                        };
-                    }
-                });
+/// Build a typed polars series from the fields of one column
+fn fields_to_series<'a>(
+    name: &str,
+    data_type: DataType,
+    fields: impl Iterator<Item = &'a Field>,
+) -> Series {
+    match data_type {
+        DataType::BOOLEAN => Series::new(
+            name,
+            fields.map(|field| field.bool_value).collect::<Vec<_>>(),
+        ),
+        DataType::INT32 => Series::new(
+            name,
+            fields.map(|field| field.int_value).collect::<Vec<_>>(),
+        ),
+        DataType::INT64 => Series::new(
+            name,
+            fields.map(|field| field.long_value).collect::<Vec<_>>(),
+        ),
+        DataType::FLOAT => Series::new(
+            name,
+            fields.map(|field| field.float_value).collect::<Vec<_>>(),
+        ),
+        DataType::DOUBLE => Series::new(
+            name,
+            fields.map(|field| field.double_value).collect::<Vec<_>>(),
+        ),
+        DataType::TEXT => Series::new(
+            name,
+            fields
+                .map(|field| {
+                    field
+                        .binary_value
+                        .as_ref()
+                        .map(|binary| String::from_utf8_lossy(binary).to_string())
+                })
+                .collect::<Vec<_>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use thrift::protocol::{
+        TBinaryInputProtocol, TBinaryOutputProtocol, TFieldIdentifier, TInputProtocol,
+        TMessageIdentifier, TMessageType, TOutputProtocol, TStructIdentifier, TType,
+    };
+
+    use super::*;
+    use crate::common::TSStatus;
+    use crate::schema::ColumnSchema;
+    use crate::Config;
+
+    fn bitmap(valid: &[bool]) -> Vec<u8> {
+        let mut bitmap = vec![0; valid.len().div_ceil(8)];
+        for (row, valid) in valid.iter().enumerate() {
+            if *valid {
+                bitmap[row / 8] |= 0x80 >> (row % 8);
+            }
+        }
+        bitmap
+    }
+
+    fn time(rows: usize) -> Vec<u8> {
+        (0..rows as i64).flat_map(|ts| ts.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn decode_sparse_column_over_eight_rows() {
+        let rows = 20;
+        let valid: Vec<bool> = (0..rows).map(|row| row % 3 != 0).collect();
+        let values: Vec<u8> = (0..rows as i32)
+            .filter(|row| row % 3 != 0)
+            .flat_map(|row| (row * 10).to_be_bytes())
+            .collect();
+        let data_set = TSQueryDataSet::new(time(rows), vec![values], vec![bitmap(&valid)]);
+
+        let mut batch = RecordBatch::new(
+            vec!["root.sg.d1.s1".to_string()],
+            &[DataType::INT32],
+            TimestampPrecision::default(),
+        );
+        batch.append(&data_set, &[0]).unwrap();
+
+        let rows: Vec<ValueRow> = batch.rows().collect();
+        assert_eq!(rows.len(), 20);
+        for (row, value_row) in rows.iter().enumerate() {
+            assert_eq!(value_row.timestamp(), row as i64);
+            let expected = if row % 3 != 0 {
+                Some(row as i32 * 10)
+            } else {
+                None
+            };
+            assert_eq!(value_row.get_by_index::<Option<i32>>(0).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn decode_sparse_text_column() {
+        let valid = [
+            true, false, false, false, false, false, false, false, false, true,
+        ];
+        let mut values = vec![];
+        for text in ["first", "tenth"] {
+            values.extend_from_slice(&(text.len() as i32).to_be_bytes());
+            values.extend_from_slice(text.as_bytes());
+        }
+        let data_set = TSQueryDataSet::new(time(10), vec![values], vec![bitmap(&valid)]);
+
+        let mut batch = RecordBatch::new(
+            vec!["root.sg.d1.s1".to_string()],
+            &[DataType::TEXT],
+            TimestampPrecision::default(),
+        );
+        batch.append(&data_set, &[0]).unwrap();
+
+        let texts: Vec<Option<String>> = batch
+            .rows()
+            .map(|row| row.get("root.sg.d1.s1").unwrap())
+            .collect();
+        assert_eq!(texts[0].as_deref(), Some("first"));
+        assert!(texts[1..9].iter().all(Option::is_none));
+        assert_eq!(texts[9].as_deref(), Some("tenth"));
+    }
+
+    #[test]
+    fn column_and_points_of_one_series() {
+        let valid = [true, false, true];
+        let values: Vec<u8> = [1.5f64, 3.5].iter().flat_map(|v| v.to_be_bytes()).collect();
+        let page = TSQueryDataSet::new(time(3), vec![values], vec![bitmap(&valid)]);
+        let mut data_set = DataSet {
+            record_batch: RecordBatch::new(
+                vec!["root.sg.d1.s1".to_string()],
+                &[DataType::DOUBLE],
+                TimestampPrecision::default(),
+            ),
+            ..DataSet::default()
+        };
+        data_set.record_batch.append(&page, &[0]).unwrap();
+
+        let (timestamps, values) = data_set.column::<f64>("root.sg.d1.s1").unwrap();
+        assert_eq!(timestamps, [0, 1, 2]);
+        assert_eq!(values, [Some(1.5), None, Some(3.5)]);
+        assert!(data_set.column::<i32>("root.sg.d1.s1").is_err());
+        assert!(data_set.column::<f64>("root.sg.d1.s2").is_err());
+
+        let points: Vec<(i64, f64)> = data_set
+            .points("root.sg.d1.s1")
+            .unwrap()
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(points, [(0, 1.5), (2, 3.5)]);
+    }
 
-                table.add_row(PrettyRow::new(value_cells));
+    #[test]
+    fn decode_truncated_values_returns_error() {
+        let valid = [true; 10];
+        // only 9 of 10 values are present
+        let values: Vec<u8> = (0..9i64).flat_map(|value| value.to_be_bytes()).collect();
+        let data_set = TSQueryDataSet::new(time(10), vec![values], vec![bitmap(&valid)]);
+
+        let mut batch = RecordBatch::new(
+            vec!["root.sg.d1.s1".to_string()],
+            &[DataType::INT64],
+            TimestampPrecision::default(),
+        );
+        let result = batch.append(&data_set, &[0]);
+        assert!(matches!(result, Err(IotDBError::Decode(_))));
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn decode_short_bitmap_returns_error() {
+        let values: Vec<u8> = (0..9).map(|_| 1).collect();
+        let data_set = TSQueryDataSet::new(time(9), vec![values], vec![vec![0xFF]]);
+
+        let mut batch = RecordBatch::new(
+            vec!["root.sg.d1.s1".to_string()],
+            &[DataType::BOOLEAN],
+            TimestampPrecision::default(),
+        );
+        let result = batch.append(&data_set, &[0]);
+        assert!(matches!(result, Err(IotDBError::Decode(_))));
+    }
+
+    #[test]
+    fn decode_truncated_text_returns_error() {
+        let mut values = vec![];
+        values.extend_from_slice(&16i32.to_be_bytes());
+        values.extend_from_slice(b"short");
+        let data_set = TSQueryDataSet::new(time(1), vec![values], vec![bitmap(&[true])]);
+
+        let mut batch = RecordBatch::new(
+            vec!["root.sg.d1.s1".to_string()],
+            &[DataType::TEXT],
+            TimestampPrecision::default(),
+        );
+        let result = batch.append(&data_set, &[0]);
+        assert!(matches!(result, Err(IotDBError::Decode(_))));
+    }
+
+    #[test]
+    fn decode_broken_non_align_page_keeps_columns() {
+        let mut data_set = NonAlignDataSet::new(
+            &["root.sg.d1.s1".to_string()],
+            &[DataType::DOUBLE],
+            None,
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::default(),
+            None,
+        );
+        // two timestamps but only one value
+        let page = TSQueryNonAlignDataSet::new(vec![time(2)], vec![1.5f64.to_be_bytes().to_vec()]);
+        let result = data_set.append(page);
+        assert!(matches!(result, Err(IotDBError::Decode(_))));
+        assert!(data_set.columns()[0].is_empty());
+    }
+
+    #[test]
+    fn to_df_keeps_nulls_and_adds_time_column() {
+        let mut values = vec![];
+        values.extend_from_slice(&10i32.to_be_bytes());
+        values.extend_from_slice(&30i32.to_be_bytes());
+        let mut texts = vec![];
+        texts.extend_from_slice(&2i32.to_be_bytes());
+        texts.extend_from_slice(b"on");
+        let page = TSQueryDataSet::new(
+            time(3),
+            vec![values, texts],
+            vec![bitmap(&[true, false, true]), bitmap(&[false, true, false])],
+        );
+        let resp = query_resp(
+            &["root.sg.d1.s1", "root.sg.d1.s2"],
+            &["INT32", "TEXT"],
+            page,
+        );
+        let mut data_set = DataSet::new(
+            resp,
+            None,
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::Millisecond,
+        )
+        .unwrap();
+
+        let df = data_set.to_df().unwrap();
+        assert_eq!(
+            df.get_column_names(),
+            ["Time", "root.sg.d1.s1", "root.sg.d1.s2"]
+        );
+        let time = df.column("Time").unwrap();
+        assert!(matches!(
+            time.dtype(),
+            polars::prelude::DataType::Datetime(TimeUnit::Milliseconds, Some(_))
+        ));
+        let timestamps: Vec<Option<i64>> = time.datetime().unwrap().into_iter().collect();
+        assert_eq!(timestamps, [Some(0), Some(1), Some(2)]);
+        let s1: Vec<Option<i32>> = df
+            .column("root.sg.d1.s1")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(s1, [Some(10), None, Some(30)]);
+        let s2: Vec<Option<&str>> = df
+            .column("root.sg.d1.s2")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(s2, [None, Some("on"), None]);
+    }
+
+    #[test]
+    fn time_series_nulls_overflowing_microseconds() {
+        let series = time_series(
+            "Time",
+            &[Some(1), Some(i64::MAX / 100), None],
+            TimestampPrecision::Microsecond,
+            "UTC",
+        );
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.null_count(), 2);
+    }
+
+    #[cfg(feature = "arrow")]
+    fn arrow_column<'a>(
+        batch: &'a arrow::record_batch::RecordBatch,
+        name: &str,
+    ) -> &'a arrow::array::ArrayRef {
+        batch.column(batch.schema().index_of(name).unwrap())
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn to_arrow_keeps_types_nulls_and_time() {
+        use arrow::array::{Array, AsArray};
+        use arrow::datatypes::{
+            DataType as ArrowDataType, Float32Type, Float64Type, Int32Type, Int64Type,
+            TimeUnit as ArrowTimeUnit, TimestampMillisecondType,
+        };
+
+        let mut texts = vec![];
+        texts.extend_from_slice(&1i32.to_be_bytes());
+        texts.extend_from_slice(b"a");
+        let page = TSQueryDataSet::new(
+            time(3),
+            vec![
+                vec![1, 0],
+                [7i32, 8].iter().flat_map(|v| v.to_be_bytes()).collect(),
+                [9i64, 10].iter().flat_map(|v| v.to_be_bytes()).collect(),
+                1.5f32.to_be_bytes().to_vec(),
+                2.5f64.to_be_bytes().to_vec(),
+                texts,
+            ],
+            vec![
+                bitmap(&[true, false, true]),
+                bitmap(&[false, true, true]),
+                bitmap(&[true, true, false]),
+                bitmap(&[true, false, false]),
+                bitmap(&[false, false, true]),
+                bitmap(&[false, true, false]),
+            ],
+        );
+        let resp = query_resp(
+            &["b", "i", "l", "f", "d", "t"],
+            &["BOOLEAN", "INT32", "INT64", "FLOAT", "DOUBLE", "TEXT"],
+            page,
+        );
+        let batch = DataSet::new(
+            resp,
+            None,
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::Millisecond,
+        )
+        .unwrap()
+        .to_arrow()
+        .unwrap();
+
+        let schema = batch.schema();
+        let types: Vec<(&str, &ArrowDataType, bool)> = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                (
+                    field.name().as_str(),
+                    field.data_type(),
+                    field.is_nullable(),
+                )
             })
+            .collect();
+        assert_eq!(
+            types,
+            [
+                (
+                    "Time",
+                    &ArrowDataType::Timestamp(ArrowTimeUnit::Millisecond, None),
+                    false
+                ),
+                ("b", &ArrowDataType::Boolean, true),
+                ("i", &ArrowDataType::Int32, true),
+                ("l", &ArrowDataType::Int64, true),
+                ("f", &ArrowDataType::Float32, true),
+                ("d", &ArrowDataType::Float64, true),
+                ("t", &ArrowDataType::Utf8, true),
+            ]
+        );
+        let time = arrow_column(&batch, "Time").as_primitive::<TimestampMillisecondType>();
+        assert_eq!(time.null_count(), 0);
+        assert_eq!(time.values().to_vec(), [0, 1, 2]);
+
+        let b: Vec<_> = arrow_column(&batch, "b").as_boolean().iter().collect();
+        assert_eq!(b, [Some(true), None, Some(false)]);
+        let i: Vec<_> = arrow_column(&batch, "i")
+            .as_primitive::<Int32Type>()
+            .iter()
+            .collect();
+        assert_eq!(i, [None, Some(7), Some(8)]);
+        let l: Vec<_> = arrow_column(&batch, "l")
+            .as_primitive::<Int64Type>()
+            .iter()
+            .collect();
+        assert_eq!(l, [Some(9), Some(10), None]);
+        let f: Vec<_> = arrow_column(&batch, "f")
+            .as_primitive::<Float32Type>()
+            .iter()
+            .collect();
+        assert_eq!(f, [Some(1.5), None, None]);
+        let d: Vec<_> = arrow_column(&batch, "d")
+            .as_primitive::<Float64Type>()
+            .iter()
+            .collect();
+        assert_eq!(d, [None, None, Some(2.5)]);
+        let t: Vec<_> = arrow_column(&batch, "t")
+            .as_string::<i32>()
+            .iter()
+            .collect();
+        assert_eq!(t, [None, Some("a"), None]);
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn to_arrow_keeps_the_server_precision() {
+        use arrow::array::AsArray;
+        use arrow::datatypes::{
+            DataType as ArrowDataType, TimeUnit as ArrowTimeUnit, TimestampMicrosecondType,
+        };
+
+        let page = TSQueryDataSet::new(
+            [1_000_001i64]
+                .iter()
+                .flat_map(|ts| ts.to_be_bytes())
+                .collect(),
+            vec![1i32.to_be_bytes().to_vec()],
+            vec![bitmap(&[true])],
+        );
+        let resp = query_resp(&["root.sg.d1.s1"], &["INT32"], page);
+        let batch = DataSet::new(
+            resp,
+            None,
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::Microsecond,
+        )
+        .unwrap()
+        .to_arrow()
+        .unwrap();
+
+        assert_eq!(
+            batch.schema().field(0).data_type(),
+            &ArrowDataType::Timestamp(ArrowTimeUnit::Microsecond, None)
+        );
+        let time = arrow_column(&batch, "Time").as_primitive::<TimestampMicrosecondType>();
+        assert_eq!(time.values().to_vec(), [1_000_001]);
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn non_align_to_arrow_pads_columns_with_nulls() {
+        use arrow::array::AsArray;
+        use arrow::datatypes::{
+            DataType as ArrowDataType, Float32Type, Float64Type, Int32Type, Int64Type,
+            TimeUnit as ArrowTimeUnit, TimestampMillisecondType,
+        };
+
+        let times = |timestamps: &[i64]| -> Vec<u8> {
+            timestamps.iter().flat_map(|ts| ts.to_be_bytes()).collect()
+        };
+        let mut texts = vec![];
+        for text in ["x", "yz"] {
+            texts.extend_from_slice(&(text.len() as i32).to_be_bytes());
+            texts.extend_from_slice(text.as_bytes());
         }
-        if !table.is_empty() {
-            table.printstd();
+        let mut resp = query_resp(
+            &["b", "i", "l", "f", "d", "t"],
+            &["BOOLEAN", "INT32", "INT64", "FLOAT", "DOUBLE", "TEXT"],
+            None,
+        );
+        resp.non_align_query_data_set = Some(TSQueryNonAlignDataSet::new(
+            vec![
+                times(&[1]),
+                times(&[1, 2]),
+                times(&[3]),
+                times(&[4, 5]),
+                times(&[6]),
+                times(&[7, 8]),
+            ],
+            vec![
+                vec![1],
+                [7i32, 8].iter().flat_map(|v| v.to_be_bytes()).collect(),
+                9i64.to_be_bytes().to_vec(),
+                [1.5f32, 2.5].iter().flat_map(|v| v.to_be_bytes()).collect(),
+                0.5f64.to_be_bytes().to_vec(),
+                texts,
+            ],
+        ));
+        let batch = DataSet::new(
+            resp,
+            None,
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::Millisecond,
+        )
+        .unwrap()
+        .to_arrow()
+        .unwrap();
+
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        let types: Vec<(&str, &ArrowDataType)> = schema
+            .fields()
+            .iter()
+            .filter(|field| !field.name().starts_with("Time("))
+            .map(|field| (field.name().as_str(), field.data_type()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("b", &ArrowDataType::Boolean),
+                ("i", &ArrowDataType::Int32),
+                ("l", &ArrowDataType::Int64),
+                ("f", &ArrowDataType::Float32),
+                ("d", &ArrowDataType::Float64),
+                ("t", &ArrowDataType::Utf8),
+            ]
+        );
+        assert!(schema.fields().iter().all(|field| field.is_nullable()));
+        assert_eq!(
+            schema.field_with_name("Time(b)").unwrap().data_type(),
+            &ArrowDataType::Timestamp(ArrowTimeUnit::Millisecond, None)
+        );
+
+        let time = |name: &str| -> Vec<Option<i64>> {
+            arrow_column(&batch, name)
+                .as_primitive::<TimestampMillisecondType>()
+                .iter()
+                .collect()
+        };
+        assert_eq!(time("Time(b)"), [Some(1), None]);
+        assert_eq!(time("Time(t)"), [Some(7), Some(8)]);
+
+        let b: Vec<_> = arrow_column(&batch, "b").as_boolean().iter().collect();
+        assert_eq!(b, [Some(true), None]);
+        let i: Vec<_> = arrow_column(&batch, "i")
+            .as_primitive::<Int32Type>()
+            .iter()
+            .collect();
+        assert_eq!(i, [Some(7), Some(8)]);
+        let l: Vec<_> = arrow_column(&batch, "l")
+            .as_primitive::<Int64Type>()
+            .iter()
+            .collect();
+        assert_eq!(l, [Some(9), None]);
+        let f: Vec<_> = arrow_column(&batch, "f")
+            .as_primitive::<Float32Type>()
+            .iter()
+            .collect();
+        assert_eq!(f, [Some(1.5), Some(2.5)]);
+        let d: Vec<_> = arrow_column(&batch, "d")
+            .as_primitive::<Float64Type>()
+            .iter()
+            .collect();
+        assert_eq!(d, [Some(0.5), None]);
+        let t: Vec<_> = arrow_column(&batch, "t")
+            .as_string::<i32>()
+            .iter()
+            .collect();
+        assert_eq!(t, [Some("x"), Some("yz")]);
+    }
+
+    #[test]
+    fn non_align_columns_keep_their_own_time_lists() {
+        let s1_time: Vec<u8> = [5i64, 6, 7]
+            .iter()
+            .flat_map(|ts| ts.to_be_bytes())
+            .collect();
+        let s1_values: Vec<u8> = [1i64, 2, 3].iter().flat_map(|v| v.to_be_bytes()).collect();
+        let s2_time = 6i64.to_be_bytes().to_vec();
+        let s2_values = 0.5f64.to_be_bytes().to_vec();
+        let mut resp = query_resp(
+            &["root.sg.d1.s1", "root.sg.d1.s2"],
+            &["INT64", "DOUBLE"],
+            None,
+        );
+        // the server sends the columns in the order of the index map
+        resp.column_name_index_map = Some(BTreeMap::from([
+            ("root.sg.d1.s1".to_string(), 1),
+            ("root.sg.d1.s2".to_string(), 0),
+        ]));
+        resp.non_align_query_data_set = Some(TSQueryNonAlignDataSet::new(
+            vec![s2_time, s1_time],
+            vec![s2_values, s1_values],
+        ));
+        let data_set = DataSet::new(
+            resp,
+            None,
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::Millisecond,
+        )
+        .unwrap();
+
+        let mut non_align = data_set.into_non_align().unwrap();
+        let s1 = non_align.column("root.sg.d1.s1").unwrap();
+        assert_eq!(s1.timestamps(), [5, 6, 7]);
+        assert_eq!(
+            s1.fields().iter().map(|f| f.long_value).collect::<Vec<_>>(),
+            [Some(1), Some(2), Some(3)]
+        );
+        let s2 = non_align.column("root.sg.d1.s2").unwrap();
+        assert_eq!(s2.timestamps(), [6]);
+        assert_eq!(s2.fields()[0].double_value, Some(0.5));
+
+        let df = non_align.to_df().unwrap();
+        assert_eq!(
+            df.get_column_names(),
+            [
+                "Time(root.sg.d1.s1)",
+                "root.sg.d1.s1",
+                "Time(root.sg.d1.s2)",
+                "root.sg.d1.s2"
+            ]
+        );
+        let time: Vec<Option<i64>> = df
+            .column("Time(root.sg.d1.s2)")
+            .unwrap()
+            .datetime()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(time, [Some(6), None, None]);
+        let values: Vec<Option<f64>> = df
+            .column("root.sg.d1.s2")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(values, [Some(0.5), None, None]);
+    }
+
+    #[test]
+    fn get_converts_by_type() {
+        let mut field = Field::new(DataType::FLOAT);
+        field.float_value = Some(1.5);
+        let mut row = ValueRow::new();
+        row.add_field(field).add_field(Field::new(DataType::INT64));
+        row.set_columns(vec![
+            "root.sg.d1.temperature".to_string(),
+            "root.sg.d1.counter".to_string(),
+        ]);
+
+        assert_eq!(row.get::<f64>("root.sg.d1.temperature").unwrap(), 1.5);
+        assert_eq!(row.get::<f32>("root.sg.d1.temperature").unwrap(), 1.5);
+        assert!(matches!(
+            row.get::<i32>("root.sg.d1.temperature"),
+            Err(IotDBError::Conversion(_))
+        ));
+        assert_eq!(row.get_by_index::<Option<i64>>(1).unwrap(), None);
+        assert!(matches!(
+            row.get_by_index::<i64>(1),
+            Err(IotDBError::Conversion(_))
+        ));
+        assert!(matches!(
+            row.get::<f64>("root.sg.d1.missing"),
+            Err(IotDBError::ColumnNotFound(_))
+        ));
+    }
+
+    /// Records the requests sent by an in-memory client
+    #[derive(Clone, Default)]
+    struct Requests(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for Requests {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Requests {
+        /// Names of the sent calls
+        fn names(&self) -> Vec<String> {
+            let bytes = self.0.borrow().clone();
+            let mut i_prot = TBinaryInputProtocol::new(io::Cursor::new(bytes), true);
+            let mut names = vec![];
+            while let Ok(ident) = i_prot.read_message_begin() {
+                i_prot.skip(TType::Struct).unwrap();
+                i_prot.read_message_end().unwrap();
+                names.push(ident.name);
+            }
+            names
         }
     }
+
+    /// Replies of an in-memory server, in the order of the calls
+    #[derive(Default)]
+    struct Replies {
+        bytes: Vec<u8>,
+        count: i32,
+    }
+
+    impl Replies {
+        /// Add a reply whose result struct holds the response in field 0
+        fn add(
+            mut self,
+            name: &str,
+            write_success: impl FnOnce(&mut dyn TOutputProtocol) -> thrift::Result<()>,
+        ) -> Self {
+            // the client numbers its calls from 1
+            self.count += 1;
+            let sequence_number = self.count;
+            let mut o_prot = TBinaryOutputProtocol::new(&mut self.bytes, true);
+            let ident = TMessageIdentifier::new(name, TMessageType::Reply, sequence_number);
+            o_prot.write_message_begin(&ident).unwrap();
+            o_prot
+                .write_struct_begin(&TStructIdentifier::new("result"))
+                .unwrap();
+            o_prot
+                .write_field_begin(&TFieldIdentifier::new("success", TType::Struct, 0))
+                .unwrap();
+            write_success(&mut o_prot).unwrap();
+            o_prot.write_field_end().unwrap();
+            o_prot.write_field_stop().unwrap();
+            o_prot.write_struct_end().unwrap();
+            o_prot.write_message_end().unwrap();
+            self
+        }
+
+        fn fetch_results(self, page: Option<TSQueryDataSet>) -> Self {
+            let status = TSStatus::new(SUCCESS_CODE, None, None, None);
+            let resp = TSFetchResultsResp::new(status, page.is_some(), true, page, None);
+            self.add("fetchResults", |o_prot| resp.write_to_out_protocol(o_prot))
+        }
+
+        fn close_operation(self) -> Self {
+            let status = TSStatus::new(SUCCESS_CODE, None, None, None);
+            self.add("closeOperation", |o_prot| {
+                status.write_to_out_protocol(o_prot)
+            })
+        }
+
+        /// Client which reads these replies and records its requests
+        fn client(self, requests: &Requests) -> Rc<RefCell<ClientType>> {
+            let i_prot = TBinaryInputProtocol::new(io::Cursor::new(self.bytes), true);
+            let o_prot = TBinaryOutputProtocol::new(requests.clone(), true);
+            Rc::new(RefCell::new(ClientType::new(
+                Box::new(i_prot),
+                Box::new(o_prot),
+            )))
+        }
+    }
+
+    fn int32_page(values: &[i32]) -> TSQueryDataSet {
+        TSQueryDataSet::new(
+            values
+                .iter()
+                .flat_map(|value| (*value as i64).to_be_bytes())
+                .collect(),
+            vec![values
+                .iter()
+                .flat_map(|value| value.to_be_bytes())
+                .collect()],
+            vec![bitmap(&vec![true; values.len()])],
+        )
+    }
+
+    fn query_resp(
+        columns: &[&str],
+        data_types: &[&str],
+        page: impl Into<Option<TSQueryDataSet>>,
+    ) -> TSExecuteStatementResp {
+        TSExecuteStatementResp::new(
+            TSStatus::new(SUCCESS_CODE, None, None, None),
+            1,
+            columns
+                .iter()
+                .map(|column| column.to_string())
+                .collect::<Vec<_>>(),
+            "QUERY".to_string(),
+            false,
+            data_types
+                .iter()
+                .map(|data_type| data_type.to_string())
+                .collect::<Vec<_>>(),
+            page,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn paged_data_set(client: Rc<RefCell<ClientType>>, first_page: TSQueryDataSet) -> DataSet {
+        let resp = query_resp(&["root.sg.d1.s1"], &["INT32"], first_page);
+        let cancel_token = CancelToken::new(Config::default(), 1, 2);
+        let cursor = QueryCursor::new(
+            client,
+            3,
+            2,
+            1,
+            "SELECT s1 FROM root.sg.d1",
+            2,
+            0,
+            cancel_token,
+        );
+        DataSet::new(
+            resp,
+            Some(cursor),
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn cursor_fetches_pages_until_the_server_has_no_more() {
+        let requests = Requests::default();
+        let client = Replies::default()
+            .fetch_results(Some(int32_page(&[3, 4])))
+            .fetch_results(None)
+            .close_operation()
+            .client(&requests);
+        let data_set = paged_data_set(client, int32_page(&[1, 2]));
+
+        let values: Vec<i32> = data_set
+            .map(|row| row.unwrap().get_by_index(0).unwrap())
+            .collect();
+        assert_eq!(values, [1, 2, 3, 4]);
+        assert_eq!(
+            requests.names(),
+            ["fetchResults", "fetchResults", "closeOperation"]
+        );
+    }
+
+    #[test]
+    fn cursor_closes_query_on_drop() {
+        let requests = Requests::default();
+        let client = Replies::default().close_operation().client(&requests);
+        let mut data_set = paged_data_set(client, int32_page(&[1, 2]));
+
+        assert!(data_set.next().unwrap().is_ok());
+        drop(data_set);
+        assert_eq!(requests.names(), ["closeOperation"]);
+    }
+
+    #[test]
+    #[cfg(feature = "parquet")]
+    fn compact_keeps_unconsumed_text() {
+        let mut batch = RecordBatch {
+            columns: Arc::new(vec!["root.sg.d1.s1".to_string()]),
+            timestamps: vec![1, 2, 3],
+            values: vec![ColumnValues::Text {
+                data: b"abcdef".to_vec(),
+                ranges: vec![Some((0, 2)), None, Some((2, 6))],
+            }],
+            offset: 2,
+            ..RecordBatch::default()
+        };
+        batch.compact();
+
+        assert_eq!(batch.timestamps, vec![3]);
+        assert_eq!(batch.row(0).get::<String>("root.sg.d1.s1").unwrap(), "cdef");
+    }
+
+    fn schema_of(resp: TSExecuteStatementResp) -> Schema {
+        DataSet::new(
+            resp,
+            None,
+            SessionTimeZone::default(),
+            None,
+            TimestampPrecision::default(),
+        )
+        .unwrap()
+        .schema()
+        .clone()
+    }
+
+    #[test]
+    fn schema_maps_indexes_aliases_and_storage_groups() {
+        let mut resp = query_resp(
+            &["root.sg.d1.s1", "avg", "root.sg.d1.s2"],
+            &["INT32", "DOUBLE", "TEXT"],
+            None,
+        );
+        resp.column_name_index_map = Some(BTreeMap::from([
+            ("root.sg.d1.s1".to_string(), 1),
+            ("root.sg.d1.s2".to_string(), 0),
+        ]));
+        resp.sg_columns = Some(vec![
+            "root.sg".to_string(),
+            String::new(),
+            "root.sg".to_string(),
+        ]);
+        resp.alias_columns = Some(vec![0, 1]);
+        let schema = schema_of(resp);
+
+        assert_eq!(schema.operation_type.as_deref(), Some("QUERY"));
+        assert_eq!(
+            schema.columns,
+            [
+                ColumnSchema {
+                    name: "root.sg.d1.s1".to_string(),
+                    data_type: DataType::INT32,
+                    storage_group: Some("root.sg".to_string()),
+                    is_alias: false,
+                    index: 1,
+                },
+                ColumnSchema {
+                    name: "avg".to_string(),
+                    data_type: DataType::DOUBLE,
+                    storage_group: None,
+                    is_alias: true,
+                    index: 1,
+                },
+                ColumnSchema {
+                    name: "root.sg.d1.s2".to_string(),
+                    data_type: DataType::TEXT,
+                    storage_group: Some("root.sg".to_string()),
+                    is_alias: false,
+                    index: 0,
+                },
+            ]
+        );
+        assert_eq!(schema.column("avg").map(|column| column.index), Some(1));
+
+        let schema = schema_of(query_resp(&["s1", "s2"], &["INT32", "INT64"], None));
+        let indexes: Vec<usize> = schema.columns.iter().map(|column| column.index).collect();
+        assert_eq!(indexes, [0, 1]);
+        assert!(schema
+            .columns
+            .iter()
+            .all(|column| column.storage_group.is_none() && !column.is_alias));
+    }
 }
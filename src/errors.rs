@@ -10,4 +10,33 @@ pub enum IotDBError {
 
     #[error("IoTDB Polars DataFrame error")]
     Polars(#[from] polars::error::PolarsError),
+
+    #[cfg(feature = "arrow")]
+    #[error("IoTDB Arrow error")]
+    Arrow(#[from] arrow::error::ArrowError),
+
+    #[error("IoTDB query data set decode error: {0}")]
+    Decode(String),
+
+    #[error("Column '{0}' doesn't exist")]
+    ColumnNotFound(String),
+
+    #[error("Value conversion error: {0}")]
+    Conversion(String),
+
+    #[error("Deserialize error: {0}")]
+    Deserialize(String),
+
+    #[error("Parameter {0} is not bound")]
+    MissingParameter(String),
+
+    #[error("Parameter {0} doesn't exist in the statement")]
+    UnknownParameter(String),
+
+    #[error("Parameter {name} expects a {expected}, got {found}")]
+    ParameterKind {
+        name: String,
+        expected: String,
+        found: String,
+    },
 }
@@ -0,0 +1,464 @@
+//! Export query results into text formats
+
+use std::fmt::{self, Write as _};
+use std::io;
+
+use crate::ds::{ColumnValues, RecordBatch};
+use crate::errors::IotDBError;
+use crate::time_zone::SessionTimeZone;
+use crate::timestamp::Timestamp;
+
+/// How timestamps are written by the exporters
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// The raw timestamp since the unix epoch, in the precision of the server
+    #[default]
+    Epoch,
+    /// RFC 3339 with the digits of the server precision, e.g. `2022-01-01T08:00:00.000+08:00`
+    Rfc3339,
+    /// A `strftime` pattern, e.g. `%Y-%m-%d %H:%M:%S%.3f`
+    Strftime(String),
+}
+
+impl TimeFormat {
+    pub(crate) fn format(
+        &self,
+        timestamp: Timestamp,
+        time_zone: &SessionTimeZone,
+        buffer: &mut String,
+    ) {
+        match self {
+            TimeFormat::Epoch => write!(buffer, "{}", timestamp.value()),
+            TimeFormat::Rfc3339 => match time_zone.to_rfc3339(timestamp) {
+                None => write!(buffer, "{}", timestamp.value()),
+                Some(date_time) => buffer.write_str(&date_time),
+            },
+            TimeFormat::Strftime(format) => {
+                time_zone.write(timestamp, Some(format), buffer);
+                Ok(())
+            }
+        }
+        .unwrap()
+    }
+}
+
+/// Options of [`DataSet::write_csv`](crate::DataSet::write_csv)
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    /// Write a header record with `Time` and the column names
+    pub header: bool,
+    pub time_format: TimeFormat,
+    /// Zone of the formatted timestamps, the time zone of the data set if it's `None`
+    pub time_zone: Option<SessionTimeZone>,
+    /// Written for null values
+    pub null_value: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            time_format: TimeFormat::default(),
+            time_zone: None,
+            null_value: String::new(),
+        }
+    }
+}
+
+/// Writes record batches as CSV records, TEXT values are quoted when needed
+pub(crate) struct CsvWriter<'a, W: io::Write> {
+    writer: csv::Writer<W>,
+    options: &'a CsvOptions,
+    time_zone: SessionTimeZone,
+    with_time: bool,
+    buffer: String,
+}
+
+impl<'a, W: io::Write> CsvWriter<'a, W> {
+    pub(crate) fn new(
+        writer: W,
+        options: &'a CsvOptions,
+        time_zone: SessionTimeZone,
+        columns: &[String],
+        with_time: bool,
+    ) -> Result<Self, IotDBError> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .from_writer(writer);
+        if options.header {
+            if with_time {
+                writer.write_field("Time").map_err(csv_error)?;
+            }
+            writer.write_record(columns).map_err(csv_error)?;
+        }
+        Ok(Self {
+            writer,
+            options,
+            time_zone: options.time_zone.unwrap_or(time_zone),
+            with_time,
+            buffer: String::new(),
+        })
+    }
+
+    /// Write the rows of the batch starting at its offset
+    pub(crate) fn write_batch(&mut self, batch: &RecordBatch) -> Result<(), IotDBError> {
+        for row in batch.offset..batch.timestamps.len() {
+            if self.with_time {
+                self.buffer.clear();
+                self.options.time_format.format(
+                    Timestamp::new(batch.timestamps[row], batch.precision),
+                    &self.time_zone,
+                    &mut self.buffer,
+                );
+                self.writer.write_field(&self.buffer).map_err(csv_error)?;
+            }
+            for values in &batch.values {
+                self.write_value(values, row)?;
+            }
+            self.writer.write_record(None::<&[u8]>).map_err(csv_error)?;
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, values: &ColumnValues, row: usize) -> Result<(), IotDBError> {
+        self.buffer.clear();
+        let buffer = &mut self.buffer;
+        let field: Option<&[u8]> = match values {
+            ColumnValues::Boolean(values) => format_value(buffer, values[row]),
+            ColumnValues::Int32(values) => format_value(buffer, values[row]),
+            ColumnValues::Int64(values) => format_value(buffer, values[row]),
+            ColumnValues::Float(values) => format_value(buffer, values[row]),
+            ColumnValues::Double(values) => format_value(buffer, values[row]),
+            ColumnValues::Text { data, ranges } => {
+                ranges[row].map(|(start, end)| &data[start..end])
+            }
+        };
+        self.writer
+            .write_field(field.unwrap_or(self.options.null_value.as_bytes()))
+            .map_err(csv_error)
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<(), IotDBError> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Layout of the JSON output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// One JSON array of row objects
+    #[default]
+    Array,
+    /// Newline-delimited JSON, one row object per line
+    Lines,
+}
+
+/// How the value keys of a JSON row object are named
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyNaming {
+    /// The full column path, e.g. `root.ln.wf01.wt01.temperature`
+    #[default]
+    FullPath,
+    /// The last node of the column path, e.g. `temperature`.
+    ///
+    /// Columns whose measurement name isn't unique in the result keep their full path.
+    Measurement,
+}
+
+/// Options of [`DataSet::write_json`](crate::DataSet::write_json)
+#[derive(Clone, Debug)]
+pub struct JsonOptions {
+    pub format: JsonFormat,
+    pub key_naming: KeyNaming,
+    /// Key of the timestamp field
+    pub time_key: String,
+    /// Epoch timestamps are written as numbers, other formats as strings
+    pub time_format: TimeFormat,
+    /// Zone of the formatted timestamps, the time zone of the data set if it's `None`
+    pub time_zone: Option<SessionTimeZone>,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self {
+            format: JsonFormat::default(),
+            key_naming: KeyNaming::default(),
+            time_key: "Time".to_string(),
+            time_format: TimeFormat::default(),
+            time_zone: None,
+        }
+    }
+}
+
+/// Writes record batches as JSON row objects, nulls and non-finite floats are written as `null`
+pub(crate) struct JsonWriter<'a, W: io::Write> {
+    writer: W,
+    options: &'a JsonOptions,
+    time_zone: SessionTimeZone,
+    with_time: bool,
+    /// Escaped keys of the columns
+    keys: Vec<String>,
+    rows: usize,
+    buffer: String,
+}
+
+impl<'a, W: io::Write> JsonWriter<'a, W> {
+    pub(crate) fn new(
+        mut writer: W,
+        options: &'a JsonOptions,
+        time_zone: SessionTimeZone,
+        columns: &[String],
+        with_time: bool,
+    ) -> Result<Self, IotDBError> {
+        let keys = column_keys(columns, options.key_naming)
+            .iter()
+            .map(|key| {
+                let mut escaped = String::new();
+                write_json_string(&mut escaped, key);
+                escaped
+            })
+            .collect();
+        if options.format == JsonFormat::Array {
+            writer.write_all(b"[")?;
+        }
+        Ok(Self {
+            writer,
+            options,
+            time_zone: options.time_zone.unwrap_or(time_zone),
+            with_time,
+            keys,
+            rows: 0,
+            buffer: String::new(),
+        })
+    }
+
+    /// Write the rows of the batch starting at its offset
+    pub(crate) fn write_batch(&mut self, batch: &RecordBatch) -> Result<(), IotDBError> {
+        for row in batch.offset..batch.timestamps.len() {
+            self.buffer.clear();
+            match self.options.format {
+                JsonFormat::Array if self.rows > 0 => self.buffer.push_str(",\n"),
+                JsonFormat::Array => self.buffer.push('\n'),
+                JsonFormat::Lines => {}
+            }
+
+            self.buffer.push('{');
+            let mut separator = "";
+            if self.with_time {
+                write_json_string(&mut self.buffer, &self.options.time_key);
+                self.buffer.push(':');
+                let timestamp = Timestamp::new(batch.timestamps[row], batch.precision);
+                match &self.options.time_format {
+                    TimeFormat::Epoch => self.options.time_format.format(
+                        timestamp,
+                        &self.time_zone,
+                        &mut self.buffer,
+                    ),
+                    time_format => {
+                        let mut time = String::new();
+                        time_format.format(timestamp, &self.time_zone, &mut time);
+                        write_json_string(&mut self.buffer, &time);
+                    }
+                }
+                separator = ",";
+            }
+            for (key, values) in self.keys.iter().zip(&batch.values) {
+                self.buffer.push_str(separator);
+                self.buffer.push_str(key);
+                self.buffer.push(':');
+                write_json_value(&mut self.buffer, values, row);
+                separator = ",";
+            }
+            self.buffer.push('}');
+            if self.options.format == JsonFormat::Lines {
+                self.buffer.push('\n');
+            }
+
+            self.writer.write_all(self.buffer.as_bytes())?;
+            self.rows += 1;
+        }
+        Ok(())
+    }
+
+    /// Close the JSON array and flush the writer
+    pub(crate) fn finish(&mut self) -> Result<(), IotDBError> {
+        if self.options.format == JsonFormat::Array {
+            self.writer.write_all(b"\n]\n")?;
+        }
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Keys of the columns, measurement names fall back to the full path when they aren't unique
+fn column_keys(columns: &[String], key_naming: KeyNaming) -> Vec<&str> {
+    columns
+        .iter()
+        .map(|column| match key_naming {
+            KeyNaming::FullPath => column.as_str(),
+            KeyNaming::Measurement => {
+                let name = measurement(column);
+                if columns
+                    .iter()
+                    .filter(|other| measurement(other) == name)
+                    .count()
+                    > 1
+                {
+                    column.as_str()
+                } else {
+                    name
+                }
+            }
+        })
+        .collect()
+}
+
+fn write_json_value(buffer: &mut String, values: &ColumnValues, row: usize) {
+    let written = match values {
+        ColumnValues::Boolean(values) => format_value(buffer, values[row]).is_some(),
+        ColumnValues::Int32(values) => format_value(buffer, values[row]).is_some(),
+        ColumnValues::Int64(values) => format_value(buffer, values[row]).is_some(),
+        ColumnValues::Float(values) => {
+            format_value(buffer, values[row].filter(|value| value.is_finite())).is_some()
+        }
+        ColumnValues::Double(values) => {
+            format_value(buffer, values[row].filter(|value| value.is_finite())).is_some()
+        }
+        ColumnValues::Text { data, ranges } => match ranges[row] {
+            None => false,
+            Some((start, end)) => {
+                write_json_string(buffer, &String::from_utf8_lossy(&data[start..end]));
+                true
+            }
+        },
+    };
+    if !written {
+        buffer.push_str("null");
+    }
+}
+
+/// Last node of a column path
+fn measurement(column: &str) -> &str {
+    column.rsplit('.').next().unwrap_or(column)
+}
+
+/// Write `value` as a quoted and escaped JSON string
+fn write_json_string(buffer: &mut String, value: &str) {
+    buffer.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(buffer, "\\u{:04x}", c as u32).unwrap(),
+            c => buffer.push(c),
+        }
+    }
+    buffer.push('"');
+}
+
+/// Format a non-null value into `buffer` and return its bytes
+fn format_value<T: fmt::Display>(buffer: &mut String, value: Option<T>) -> Option<&[u8]> {
+    let value = value?;
+    write!(buffer, "{}", value).unwrap();
+    Some(buffer.as_bytes())
+}
+
+fn csv_error(error: csv::Error) -> IotDBError {
+    match error.into_kind() {
+        csv::ErrorKind::Io(error) => IotDBError::IO(error),
+        kind => IotDBError::IO(io::Error::other(format!("{:?}", kind))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn write_csv_escapes_text_and_nulls() {
+        let text = b"a;b\"c";
+        let batch = RecordBatch {
+            columns: Arc::new(vec![
+                "root.sg.d1.s1".to_string(),
+                "root.sg.d1.s2".to_string(),
+            ]),
+            timestamps: vec![0, 1000],
+            values: vec![
+                ColumnValues::Int32(vec![Some(1), None]),
+                ColumnValues::Text {
+                    data: text.to_vec(),
+                    ranges: vec![None, Some((0, text.len()))],
+                },
+            ],
+            ..RecordBatch::default()
+        };
+        let options = CsvOptions {
+            delimiter: b';',
+            time_format: TimeFormat::Rfc3339,
+            null_value: "null".to_string(),
+            ..CsvOptions::default()
+        };
+
+        let mut output = vec![];
+        let time_zone = "UTC+8".parse().unwrap();
+        let mut writer =
+            CsvWriter::new(&mut output, &options, time_zone, &batch.columns, true).unwrap();
+        writer.write_batch(&batch).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Time;root.sg.d1.s1;root.sg.d1.s2\n\
+             1970-01-01T08:00:00.000+08:00;1;null\n\
+             1970-01-01T08:00:01.000+08:00;null;\"a;b\"\"c\"\n"
+        );
+    }
+
+    #[test]
+    fn write_json_lines_by_measurement() {
+        let batch = RecordBatch {
+            columns: Arc::new(vec![
+                "root.sg.d1.s1".to_string(),
+                "root.sg.d1.s2".to_string(),
+                "root.sg.d2.s2".to_string(),
+            ]),
+            timestamps: vec![1000],
+            values: vec![
+                ColumnValues::Double(vec![Some(f64::NAN)]),
+                ColumnValues::Text {
+                    data: b"say \"hi\"\n".to_vec(),
+                    ranges: vec![Some((0, 9))],
+                },
+                ColumnValues::Boolean(vec![None]),
+            ],
+            ..RecordBatch::default()
+        };
+        let options = JsonOptions {
+            format: JsonFormat::Lines,
+            key_naming: KeyNaming::Measurement,
+            ..JsonOptions::default()
+        };
+
+        let mut output = vec![];
+        let time_zone = SessionTimeZone::default();
+        let mut writer =
+            JsonWriter::new(&mut output, &options, time_zone, &batch.columns, true).unwrap();
+        writer.write_batch(&batch).unwrap();
+        writer.write_batch(&batch).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let line = r#"{"Time":1000,"s1":null,"root.sg.d1.s2":"say \"hi\"\n","root.sg.d2.s2":null}"#;
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}\n{}\n", line, line)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Last values of time series
+
+use crate::ds::ValueRow;
+use crate::errors::IotDBError;
+use crate::timestamp::Timestamp;
+use crate::value::Value;
+use crate::DataType;
+
+/// Latest point of a time series, see [`Session::last_values`](crate::Session::last_values)
+#[derive(Clone, Debug, PartialEq)]
+pub struct LastValue {
+    pub path: String,
+    pub time: Timestamp,
+    pub value: Value,
+    pub data_type: DataType,
+}
+
+impl LastValue {
+    /// Read a row of a last query, the server sends the path, value and type as TEXT columns
+    pub(crate) fn from_row(row: &ValueRow) -> Result<Self, IotDBError> {
+        let path: String = row.get_by_index(column_index(row, "timeseries")?)?;
+        let value: String = row.get_by_index(column_index(row, "value")?)?;
+        let data_type: String = row.get_by_index(column_index(row, "dataType")?)?;
+        let data_type: DataType = data_type.parse()?;
+        Ok(Self {
+            path,
+            time: row.time(),
+            value: parse_value(&value, data_type)?,
+            data_type,
+        })
+    }
+}
+
+/// Index of a column of the last query header, the case differs between server versions
+fn column_index(row: &ValueRow, name: &str) -> Result<usize, IotDBError> {
+    row.columns()
+        .iter()
+        .position(|column| column.eq_ignore_ascii_case(name))
+        .ok_or_else(|| IotDBError::ColumnNotFound(name.to_string()))
+}
+
+fn parse_value(value: &str, data_type: DataType) -> Result<Value, IotDBError> {
+    let error = || IotDBError::Conversion(format!("'{}' is not a {:?} value", value, data_type));
+    match data_type {
+        DataType::BOOLEAN => value.parse().map(Value::Bool).map_err(|_| error()),
+        DataType::INT32 => value.parse().map(Value::Int32).map_err(|_| error()),
+        DataType::INT64 => value.parse().map(Value::Int64).map_err(|_| error()),
+        DataType::FLOAT => value.parse().map(Value::Float).map_err(|_| error()),
+        DataType::DOUBLE => value.parse().map(Value::Double).map_err(|_| error()),
+        DataType::TEXT => Ok(Value::Text(value.as_bytes().to_vec())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ds::Field;
+    use crate::timestamp::TimestampPrecision;
+
+    fn row(value: &str, data_type: &str) -> ValueRow {
+        let mut row = ValueRow::new();
+        row.set_timestamp(1000).set_columns(vec![
+            "timeseries".to_string(),
+            "value".to_string(),
+            "dataType".to_string(),
+        ]);
+        for text in ["root.sg.d1.s1", value, data_type] {
+            let mut field = Field::new(DataType::TEXT);
+            field.binary_value = Some(text.as_bytes().to_vec());
+            row.add_field(field);
+        }
+        row
+    }
+
+    #[test]
+    fn last_value_from_row() {
+        assert_eq!(
+            LastValue::from_row(&row("36.5", "DOUBLE")).unwrap(),
+            LastValue {
+                path: "root.sg.d1.s1".to_string(),
+                time: Timestamp::new(1000, TimestampPrecision::Millisecond),
+                value: Value::Double(36.5),
+                data_type: DataType::DOUBLE,
+            }
+        );
+        assert!(matches!(
+            LastValue::from_row(&row("on", "BOOLEAN")),
+            Err(IotDBError::Conversion(_))
+        ));
+    }
+}
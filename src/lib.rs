@@ -28,7 +28,7 @@
 //!
 //! # Example
 //!
-//! ```rust
+//! ```rust,no_run
 //! use chrono::Local;
 //!
 //! use iotdb::*;
@@ -62,7 +62,8 @@
 //!         Compressor::default(),
 //!     )?;
 //!
-//!     let now = Local::now().timestamp_millis();
+//!     let now = session.to_timestamp(&Local::now())?;
+//!     let second = session.timestamp_precision().units_per_second();
 //!     session.sql(
 //!         format!(
 //!             "INSERT INTO root.ln.wf01.wt01(timestamp,status) values({},true)",
@@ -73,29 +74,29 @@
 //!     session.sql(
 //!         format!(
 //!             "INSERT INTO root.ln.wf01.wt01(timestamp,status) values({},false)",
-//!             now + 1000
+//!             now + second
 //!         )
 //!         .as_str(),
 //!     )?;
 //!     session.sql(
 //!         format!(
 //!             "INSERT INTO root.ln.wf01.wt01(timestamp,status,temperature) values({},false,18.36)",
-//!             now + 2000
+//!             now + 2 * second
 //!         )
 //!         .as_str(),
 //!     )?;
 //!     session.sql(
 //!         format!(
 //!             "INSERT INTO root.ln.wf01.wt01(timestamp,status,temperature) values({},true,32.23)",
-//!             now + 3000
+//!             now + 3 * second
 //!         )
 //!         .as_str(),
 //!     )?;
 //!     session.sql("select * from root.ln")?.show();
 //!
-//!     // DF (TODO)
+//!     // DataFrame
 //!     let df = session.sql("select * from root.ln")?.to_df()?;
-//!     println!("IoTDB DF is empty: {}", df.is_empty());
+//!     println!("{}", df);
 //!
 //!     session.close()?;
 //!
@@ -119,9 +120,10 @@
 #[macro_use]
 extern crate prettytable;
 
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::net::TcpStream;
-use std::ptr::addr_of_mut;
+use std::rc::Rc;
 use std::str::FromStr;
 
 use anyhow::bail;
@@ -134,20 +136,69 @@ pub use thrift;
 use thrift::protocol::*;
 use thrift::transport::*;
 
+pub use crate::aggregate::{AggregateQuery, AggregateResult, Aggregation, FillMethod};
+#[cfg(feature = "arrow")]
+pub use crate::arrow_batch::TIME_COLUMN;
+pub use crate::cancel::{CancelToken, QueryHandle};
 use crate::client::*;
 use crate::common::*;
-use crate::ds::DataSet;
-
+#[cfg(feature = "serde")]
+pub use crate::de::TIMESTAMP_FIELD;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use crate::ds::decode_page;
+use crate::ds::QueryCursor;
+pub use crate::ds::{DataSet, Field, NonAlignColumn, NonAlignDataSet, Points, ValueRow};
+pub use crate::errors::IotDBError;
+pub use crate::export::{CsvOptions, JsonFormat, JsonOptions, KeyNaming, TimeFormat};
+pub use crate::last::LastValue;
+#[cfg(feature = "parquet")]
+pub use crate::parquet::ParquetOptions;
+pub use crate::query::{join_path, quote_node, Comparison, Query};
+pub use crate::render::{BinaryFormat, TableOptions};
+pub use crate::schema::{ColumnSchema, Schema};
+pub use crate::statement::{Param, Statement};
+pub use crate::stats::{ColumnStats, TimeGap, TimeReport};
+pub use crate::tablet::Tablet;
+pub use crate::time_zone::SessionTimeZone;
+pub use crate::timestamp::{Timestamp, TimestampPrecision};
+pub use crate::tracing::{Activity, TracingInfo};
+pub use crate::value::{FromValue, Value};
+
+mod aggregate;
+#[cfg(feature = "arrow")]
+mod arrow_batch;
+mod cancel;
+// Generated by the thrift compiler, the client uses only part of the service
+#[allow(dead_code, clippy::match_single_binding, clippy::unnecessary_unwrap)]
 mod client;
+#[allow(dead_code)]
 mod common;
+#[cfg(feature = "serde")]
+mod de;
 mod ds;
 mod errors;
+mod export;
+mod last;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod query;
+mod render;
+mod schema;
+mod statement;
+mod stats;
+mod tablet;
+mod time_zone;
+mod timestamp;
+mod tracing;
+mod value;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 const SUCCESS_CODE: i32 = 200;
 
-type ClientType = IClientRPCServiceSyncClient<Box<dyn TInputProtocol>, Box<dyn TOutputProtocol>>;
+pub(crate) type ClientType =
+    IClientRPCServiceSyncClient<Box<dyn TInputProtocol>, Box<dyn TOutputProtocol>>;
 
 /// IotDB datatype enum
 #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -160,30 +211,36 @@ pub enum DataType {
     TEXT,
 }
 
+impl FromStr for DataType {
+    type Err = IotDBError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "BOOLEAN" => Ok(DataType::BOOLEAN),
+            "INT32" => Ok(DataType::INT32),
+            "INT64" => Ok(DataType::INT64),
+            "FLOAT" => Ok(DataType::FLOAT),
+            "DOUBLE" => Ok(DataType::DOUBLE),
+            "TEXT" => Ok(DataType::TEXT),
+            _ => Err(IotDBError::Conversion(format!(
+                "This '{}' data type doesn't exist",
+                value
+            ))),
+        }
+    }
+}
+
 impl From<&String> for DataType {
     fn from(value: &String) -> Self {
-        match value.as_str() {
-            "BOOLEAN" => DataType::BOOLEAN,
-            "INT32" => DataType::INT32,
-            "INT64" => DataType::INT64,
-            "FLOAT" => DataType::FLOAT,
-            "DOUBLE" => DataType::DOUBLE,
-            "TEXT" => DataType::TEXT,
-            _ => panic!("This '{}' data type doesn't exist", value),
-        }
+        DataType::from(value.as_str())
     }
 }
 
 impl From<&str> for DataType {
     fn from(value: &str) -> Self {
-        match value {
-            "BOOLEAN" => DataType::BOOLEAN,
-            "INT32" => DataType::INT32,
-            "INT64" => DataType::INT64,
-            "FLOAT" => DataType::FLOAT,
-            "DOUBLE" => DataType::DOUBLE,
-            "TEXT" => DataType::TEXT,
-            _ => panic!("This '{}' data type doesn't exist", value),
+        match value.parse() {
+            Ok(data_type) => data_type,
+            Err(_) => panic!("This '{}' data type doesn't exist", value),
         }
     }
 }
@@ -407,6 +464,8 @@ pub struct Config {
     pub user: String,
     pub password: String,
     pub time_zone: String,
+    /// `strftime` pattern of displayed timestamps, e.g. `%Y-%m-%d %H:%M:%S%.3f`
+    pub time_format: Option<String>,
     pub timeout: i64,
     pub fetch_size: i32,
     pub endpoint: Endpoint,
@@ -424,6 +483,7 @@ impl Default for Config {
             password: "root".to_string(),
             timeout: 3000,
             time_zone: format!("{}{}", Utc::now().offset(), Local::now().offset()),
+            time_format: None,
             fetch_size: 1024,
             rpc_compaction: false,
             protocol_version: TSProtocolVersion::IOTDB_SERVICE_PROTOCOL_V3,
@@ -472,8 +532,17 @@ impl ConfigBuilder {
         self
     }
 
+    /// Offsets like `utc+8` are upper cased, IANA names like `Asia/Shanghai` are kept as they are
     pub fn time_zone(&mut self, time_zone: &str) -> &mut Self {
-        self.0.time_zone = time_zone.to_uppercase();
+        self.0.time_zone = match time_zone.parse::<SessionTimeZone>() {
+            Ok(SessionTimeZone::Named(_)) => time_zone.to_string(),
+            _ => time_zone.to_uppercase(),
+        };
+        self
+    }
+
+    pub fn time_format(&mut self, time_format: &str) -> &mut Self {
+        self.0.time_format = Some(time_format.to_string());
         self
     }
 
@@ -512,13 +581,60 @@ impl ConfigBuilder {
     }
 }
 
-/// IotDB Session
+/// Per-call options of queries, unset options fall back to the session [`Config`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    timeout: Option<i64>,
+    fetch_size: Option<i32>,
+    enable_redirect_query: Option<bool>,
+}
+
+impl QueryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Query timeout in milliseconds, also used when fetching the remaining pages
+    pub fn timeout(mut self, timeout: i64) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Rows per page fetched from the server
+    pub fn fetch_size(mut self, fetch_size: i32) -> Self {
+        self.fetch_size = Some(fetch_size);
+        self
+    }
 
+    pub fn enable_redirect_query(mut self, enable_redirect_query: bool) -> Self {
+        self.enable_redirect_query = Some(enable_redirect_query);
+        self
+    }
+
+    fn timeout_or(&self, config: &Config) -> i64 {
+        self.timeout.unwrap_or(config.timeout)
+    }
+
+    fn fetch_size_or(&self, config: &Config) -> i32 {
+        self.fetch_size.unwrap_or(config.fetch_size)
+    }
+
+    fn enable_redirect_query_or(&self, config: &Config) -> bool {
+        self.enable_redirect_query
+            .unwrap_or(config.enable_redirect_query)
+    }
+}
+
+/// IotDB Session
 pub struct Session {
-    client: ClientType,
+    client: Rc<RefCell<ClientType>>,
     config: Config,
     session_id: i64,
     statement_id: i64,
+    timestamp_precision: TimestampPrecision,
+    /// `supported_time_aggregation_operations` of the server, `None` if they are unknown
+    time_aggregations: Option<Vec<String>>,
+    time_zone: SessionTimeZone,
     is_close: bool,
 }
 
@@ -526,6 +642,12 @@ impl Session {
     // Open Session
     pub fn connect(config: Config) -> anyhow::Result<Session> {
         debug!("{:#?}", &config);
+        if let Some(time_format) = &config.time_format {
+            if !time_zone::is_valid_time_format(time_format) {
+                bail!("Invalid time format '{}'", time_format)
+            }
+        }
+        let time_zone = Self::parse_time_zone(&config.time_zone);
         let stream = TcpStream::connect(config.endpoint.to_string())?;
         debug!("TcpStream connect to {:?}", config.endpoint);
 
@@ -580,12 +702,20 @@ impl Session {
                     statement_id,
                 );
 
+                let properties = Self::read_properties(&mut client);
+                let timestamp_precision = Self::properties_precision(properties.as_ref());
+                debug!("Server timestamp precision: {}", timestamp_precision);
+
                 Ok(Session {
-                    client,
+                    client: Rc::new(RefCell::new(client)),
                     config,
                     is_close: false,
                     session_id: session_id.unwrap(),
                     statement_id,
+                    timestamp_precision,
+                    time_aggregations: properties
+                        .map(|properties| properties.supported_time_aggregation_operations),
+                    time_zone,
                 })
             }
         } else {
@@ -595,6 +725,55 @@ impl Session {
         }
     }
 
+    /// Parse the session time zone for display and export, UTC if it's unknown
+    fn parse_time_zone(time_zone: &str) -> SessionTimeZone {
+        time_zone.parse().unwrap_or_else(|error| {
+            debug!("{}, timestamps are shown in UTC", error);
+            SessionTimeZone::default()
+        })
+    }
+
+    /// Read the server properties, `None` if the server doesn't answer them
+    fn read_properties(client: &mut ClientType) -> Option<ServerProperties> {
+        client
+            .get_properties()
+            .map_err(|error| debug!("Get server properties failed, {}", error))
+            .ok()
+    }
+
+    /// Timestamp precision of the server properties, milliseconds if it's unknown
+    fn properties_precision(properties: Option<&ServerProperties>) -> TimestampPrecision {
+        properties
+            .and_then(|properties| {
+                properties
+                    .timestamp_precision
+                    .parse()
+                    .map_err(|error| debug!("{}", error))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Precision of the timestamps on the server, read when the session is opened
+    pub fn timestamp_precision(&self) -> TimestampPrecision {
+        self.timestamp_precision
+    }
+
+    /// Convert a date time into a raw timestamp in the precision of the server,
+    /// e.g. to build the `timestamp` of [`Session::insert_record`]
+    pub fn to_timestamp<Tz: chrono::TimeZone>(
+        &self,
+        date_time: &chrono::DateTime<Tz>,
+    ) -> anyhow::Result<i64> {
+        match Timestamp::from_datetime(date_time, self.timestamp_precision) {
+            Some(timestamp) => Ok(timestamp.value()),
+            None => bail!(
+                "Date time is out of range of {} timestamps",
+                self.timestamp_precision
+            ),
+        }
+    }
+
     pub fn is_open(&self) -> bool {
         !self.is_close
     }
@@ -607,7 +786,7 @@ impl Session {
     pub fn close(&mut self) -> anyhow::Result<()> {
         if !self.is_close {
             let req = TSCloseSessionReq::new(self.session_id);
-            let status = self.client.close_session(req)?;
+            let status = self.client.borrow_mut().close_session(req)?;
             if self.is_success(&status) {
                 self.is_close = true;
                 debug!("Session closed");
@@ -629,6 +808,7 @@ impl Session {
     pub fn set_storage_group(&mut self, storage_group: &str) -> anyhow::Result<()> {
         let status = self
             .client
+            .borrow_mut()
             .set_storage_group(self.session_id, storage_group.to_string())?;
 
         if self.is_success(&status) {
@@ -657,6 +837,7 @@ impl Session {
     pub fn delete_storage_groups(&mut self, storage_groups: Vec<String>) -> anyhow::Result<()> {
         let status = self
             .client
+            .borrow_mut()
             .delete_storage_groups(self.session_id, storage_groups.clone())?;
         if self.is_success(&status) {
             debug!(
@@ -700,7 +881,7 @@ impl Session {
                 Ok(())
             }
             false => {
-                let status = self.client.create_timeseries(req)?;
+                let status = self.client.borrow_mut().create_timeseries(req)?;
                 if self.is_success(&status) {
                     debug!(
                         "Creat time series {:?}, message: {:?}",
@@ -738,7 +919,7 @@ impl Session {
             None,
             None,
         );
-        let status = self.client.create_multi_timeseries(req)?;
+        let status = self.client.borrow_mut().create_multi_timeseries(req)?;
         if self.is_success(&status) {
             debug!(
                 "Creating multiple time series {:?}, message: {:?}",
@@ -759,6 +940,7 @@ impl Session {
     pub fn delete_time_series(&mut self, path_vec: Vec<String>) -> anyhow::Result<()> {
         let status = self
             .client
+            .borrow_mut()
             .delete_timeseries(self.session_id, path_vec.clone())?;
         if self.is_success(&status) {
             debug!(
@@ -778,20 +960,20 @@ impl Session {
 
     /// Check whether a specific time-series exists
     pub fn check_time_series_exists(&mut self, path: &str) -> anyhow::Result<bool> {
-        let config = self.config.clone();
+        self.check_time_series_exists_with(path, QueryOptions::default())
+    }
+
+    /// Check whether a time-series exists with per-call options, see [`QueryOptions`]
+    pub fn check_time_series_exists_with(
+        &mut self,
+        path: &str,
+        options: QueryOptions,
+    ) -> anyhow::Result<bool> {
         let statement = format!("SHOW TIMESERIES {}", path);
-        let req = TSExecuteStatementReq::new(
-            self.session_id,
-            statement,
-            self.statement_id,
-            config.fetch_size,
-            config.timeout,
-            config.enable_redirect_query,
-            false,
-        );
+        let req = self.statement_req(&statement, &options);
 
         let TSExecuteStatementResp { query_data_set, .. } =
-            self.client.execute_query_statement(req)?;
+            self.client.borrow_mut().execute_query_statement(req)?;
         if let Some(..) = query_data_set {
             Ok(false)
         } else {
@@ -802,7 +984,7 @@ impl Session {
     /// Delete all data <= time in multiple time-series
     pub fn delete_data(&mut self, path_vec: Vec<String>, timestamp: i64) -> anyhow::Result<()> {
         let req = TSDeleteDataReq::new(self.session_id, path_vec.clone(), 0, timestamp);
-        let status = self.client.delete_data(req)?;
+        let status = self.client.borrow_mut().delete_data(req)?;
         if self.is_success(&status) {
             debug!(
                 "Delete data from {:?}, message: {:?}",
@@ -837,7 +1019,7 @@ impl Session {
             is_aligned,
         );
 
-        let status = self.client.insert_string_records(req)?;
+        let status = self.client.borrow_mut().insert_string_records(req)?;
         if self.is_success(&status) {
             debug!(
                 "Insert string records to device {:?}, message: {:?}",
@@ -872,7 +1054,7 @@ impl Session {
             is_aligned,
         );
 
-        let status = self.client.insert_record(req)?;
+        let status = self.client.borrow_mut().insert_record(req)?;
         if self.is_success(&status) {
             debug!(
                 "Insert one record to device {:?}, message: {:?}",
@@ -907,7 +1089,7 @@ impl Session {
             timestamp,
             is_aligned,
         );
-        let status = self.client.test_insert_record(req)?;
+        let status = self.client.borrow_mut().test_insert_record(req)?;
         if self.is_success(&status) {
             debug!(
                 "Testing! insert one record to prefix path {:?}, message: {:?}",
@@ -941,7 +1123,7 @@ impl Session {
             timestamps,
             is_aligned,
         );
-        let status = self.client.insert_records(req)?;
+        let status = self.client.borrow_mut().insert_records(req)?;
         if self.is_success(&status) {
             debug!(
                 "Insert multiple records to prefix path {:?}, message: {:?}",
@@ -976,7 +1158,7 @@ impl Session {
             timestamps,
             is_aligned,
         );
-        let status = self.client.test_insert_records(req)?;
+        let status = self.client.borrow_mut().test_insert_records(req)?;
         if self.is_success(&status) {
             debug!(
                 "Testing! insert multiple records, message: {:?}",
@@ -1022,10 +1204,25 @@ impl Session {
             size,
             is_aligned,
         );
-        let status = self.client.insert_tablet(req)?;
+        self.send_insert_tablet(req)
+    }
+
+    /// Insert the rows of a tablet, see [`Tablet`], an empty tablet is not sent
+    pub fn write_tablet(&mut self, tablet: &Tablet, is_aligned: bool) -> anyhow::Result<()> {
+        if tablet.is_empty() {
+            return Ok(());
+        }
+        let req = self.gen_insert_tablet_req(tablet, is_aligned);
+        self.send_insert_tablet(req)
+    }
+
+    fn send_insert_tablet(&mut self, req: TSInsertTabletReq) -> anyhow::Result<()> {
+        let device_id = req.prefix_path.clone();
+        let status = self.client.borrow_mut().insert_tablet(req)?;
         if self.is_success(&status) {
             debug!(
-                "Testing! insert multiple records, message: {:?}",
+                "Insert tablet of {}, message: {:?}",
+                device_id,
                 status.message.unwrap_or_else(|| "None".to_string())
             );
             Ok(())
@@ -1061,7 +1258,7 @@ impl Session {
             size_list,
             is_aligned,
         );
-        let status = self.client.insert_tablets(req)?;
+        let status = self.client.borrow_mut().insert_tablets(req)?;
         if self.is_success(&status) {
             debug!(
                 "Testing! insert multiple records, message: {:?}",
@@ -1077,6 +1274,22 @@ impl Session {
         }
     }
 
+    /// Insert an Arrow record batch into the device as one tablet.
+    ///
+    /// The batch needs a [`TIME_COLUMN`] timestamp column, every other column is a measurement
+    /// named by its full path or relative to the device, nulls are skipped.
+    #[cfg(feature = "arrow")]
+    pub fn insert_record_batch(
+        &mut self,
+        device_id: &str,
+        batch: &arrow::record_batch::RecordBatch,
+        is_aligned: bool,
+    ) -> anyhow::Result<()> {
+        let tablet =
+            crate::arrow_batch::batch_to_tablet(device_id, batch, self.timestamp_precision)?;
+        self.write_tablet(&tablet, is_aligned)
+    }
+
     /// TODO
     pub fn insert_records_of_one_device() {}
 
@@ -1096,34 +1309,45 @@ impl Session {
     /// TODO
     pub fn test_insert_tablets() {}
 
-    /// TODO
-    pub fn gen_insert_tablet_req() {}
+    fn gen_insert_tablet_req(&self, tablet: &Tablet, is_aligned: bool) -> TSInsertTabletReq {
+        TSInsertTabletReq::new(
+            self.session_id,
+            tablet.device_id().to_string(),
+            tablet.measurements().to_vec(),
+            tablet.serialize_values(),
+            tablet.serialize_timestamps(),
+            tablet
+                .data_types()
+                .iter()
+                .map(|data_type| (*data_type).into())
+                .collect(),
+            tablet.len() as i32,
+            is_aligned,
+        )
+    }
 
     /// TODO
     pub fn gen_insert_tablets_req() {}
 
     pub fn sql(&mut self, sql: &str) -> anyhow::Result<DataSet> {
-        self.exec(sql)
+        self.exec(sql, &QueryOptions::default())
+    }
+
+    /// Execute any statement with per-call options, see [`QueryOptions`]
+    pub fn sql_with(&mut self, sql: &str, options: QueryOptions) -> anyhow::Result<DataSet> {
+        self.exec(sql, &options)
     }
 
     /// execute query sql statement and return a DataSet
-    fn exec(&mut self, statement: &str) -> anyhow::Result<DataSet> {
+    fn exec(&mut self, statement: &str, options: &QueryOptions) -> anyhow::Result<DataSet> {
         debug!("Exec statement \"{}\"", statement);
-        let req = TSExecuteStatementReq::new(
-            self.session_id,
-            statement.to_string(),
-            self.statement_id,
-            self.config.fetch_size,
-            self.config.timeout,
-            self.config.enable_redirect_query,
-            false,
-        );
-        let resp = self.client.execute_statement(req)?;
+        let req = self.statement_req(statement, options);
+        let resp = self.client.borrow_mut().execute_statement(req)?;
         let status = resp.clone().status;
         let msg = status.clone().message.unwrap_or_else(|| "None".to_string());
         if self.is_success(&status) {
             debug!("Execute statement {:?}, message: {:?}", statement, msg);
-            Ok(DataSet::new(resp))
+            self.data_set(statement, resp, options)
         } else {
             error!("{}", msg);
             bail!(msg)
@@ -1133,7 +1357,7 @@ impl Session {
     /// execute batch statement and return a DataSets
     pub fn exec_batch(&mut self, statements: Vec<String>) -> anyhow::Result<()> {
         let req = TSExecuteBatchStatementReq::new(self.session_id, statements);
-        let status = self.client.execute_batch_statement(req)?;
+        let status = self.client.borrow_mut().execute_batch_statement(req)?;
         let msg = status.clone().message.unwrap_or_else(|| "None".to_string());
         if self.is_success(&status) {
             info!("{}", msg);
@@ -1146,18 +1370,16 @@ impl Session {
 
     /// execute query sql statement and return a DataSet
     pub fn exec_query(&mut self, query: &str) -> anyhow::Result<DataSet> {
+        self.query_with(query, QueryOptions::default())
+    }
+
+    /// Execute a query with per-call options, e.g.
+    /// `session.query_with("select * from root.sg.d1", QueryOptions::new().timeout(1000))`
+    pub fn query_with(&mut self, query: &str, options: QueryOptions) -> anyhow::Result<DataSet> {
         debug!("Exec query \"{}\"", &query);
-        let req = TSExecuteStatementReq::new(
-            self.session_id,
-            query.to_string(),
-            self.statement_id,
-            self.config.fetch_size,
-            self.config.timeout,
-            self.config.enable_redirect_query,
-            false,
-        );
+        let req = self.statement_req(query, &options);
 
-        let resp = self.client.execute_query_statement(req)?;
+        let resp = self.client.borrow_mut().execute_query_statement(req)?;
         if self.is_success(&resp.status) {
             debug!(
                 "Execute query {:?}, message: {:?}",
@@ -1167,7 +1389,7 @@ impl Session {
                     .message
                     .unwrap_or_else(|| "None".to_string())
             );
-            Ok(DataSet::new(resp))
+            self.data_set(query, resp, &options)
         } else {
             error!(
                 "Exec query failed, code: {}, reason: {}",
@@ -1183,17 +1405,18 @@ impl Session {
 
     /// execute update statement and return a DataSet
     pub fn exec_update(&mut self, statement: &str) -> anyhow::Result<DataSet> {
-        let req = TSExecuteStatementReq::new(
-            self.session_id,
-            statement.to_string(),
-            self.statement_id,
-            self.config.fetch_size,
-            self.config.timeout,
-            self.config.enable_redirect_query,
-            false,
-        );
+        self.update_with(statement, QueryOptions::default())
+    }
+
+    /// Execute an update statement with per-call options, see [`QueryOptions`]
+    pub fn update_with(
+        &mut self,
+        statement: &str,
+        options: QueryOptions,
+    ) -> anyhow::Result<DataSet> {
+        let req = self.statement_req(statement, &options);
 
-        let resp = self.client.execute_update_statement(req)?;
+        let resp = self.client.borrow_mut().execute_update_statement(req)?;
         if self.is_success(&resp.status) {
             debug!(
                 "Execute update statement {:?}, message: {:?}",
@@ -1203,7 +1426,116 @@ impl Session {
                     .message
                     .unwrap_or_else(|| "None".to_string())
             );
-            Ok(DataSet::new(resp))
+            self.data_set(statement, resp, &options)
+        } else {
+            error!(
+                "{}",
+                resp.status
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| "None".to_string())
+            );
+            bail!(resp.status.message.unwrap_or_else(|| "None".to_string()))
+        }
+    }
+
+    /// Bind the parameters of the statement and execute it like [`Session::sql`],
+    /// timestamps are converted into the precision of the server
+    pub fn exec_statement(&mut self, statement: &Statement) -> anyhow::Result<DataSet> {
+        self.exec_statement_with(statement, QueryOptions::default())
+    }
+
+    /// Execute a statement with per-call options, see [`QueryOptions`]
+    pub fn exec_statement_with(
+        &mut self,
+        statement: &Statement,
+        options: QueryOptions,
+    ) -> anyhow::Result<DataSet> {
+        let sql = statement.to_sql(self.timestamp_precision)?;
+        self.exec(&sql, &options)
+    }
+
+    /// Run a query built from typed parts through [`Session::exec_query`]
+    pub fn select(&mut self, query: &Query) -> anyhow::Result<DataSet> {
+        self.select_with(query, QueryOptions::default())
+    }
+
+    /// Run a query built from typed parts with per-call options, see [`QueryOptions`]
+    pub fn select_with(&mut self, query: &Query, options: QueryOptions) -> anyhow::Result<DataSet> {
+        self.query_with(&query.to_sql()?, options)
+    }
+
+    /// Run an aggregation query, time aggregations are checked against the server properties first
+    pub fn aggregate(&mut self, query: &AggregateQuery) -> anyhow::Result<AggregateResult> {
+        self.aggregate_with(query, QueryOptions::default())
+    }
+
+    /// Run an aggregation query with per-call options, see [`QueryOptions`]
+    pub fn aggregate_with(
+        &mut self,
+        query: &AggregateQuery,
+        options: QueryOptions,
+    ) -> anyhow::Result<AggregateResult> {
+        if let Some(supported) = &self.time_aggregations {
+            if let Some(aggregation) = query.aggregations().find(|aggregation| {
+                aggregation.is_time_aggregation()
+                    && !supported
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(aggregation.name()))
+            }) {
+                bail!("Aggregation {} is not supported by the server", aggregation)
+            }
+        }
+
+        let data_set = self.query_with(&query.to_sql()?, options)?;
+        let columns: Vec<String> = data_set
+            .schema()
+            .columns
+            .iter()
+            .map(|column| column.name.clone())
+            .collect();
+        let mut result = AggregateResult::new(&columns)?;
+        for row in data_set {
+            let row = row?;
+            let time = query.is_grouped_by_time().then(|| row.time());
+            result.push_row(time, row.values());
+        }
+        Ok(result)
+    }
+
+    /// Latest point of every path, only points at or after `since_time` are returned.
+    ///
+    /// `since_time` is in the server precision, paths without such a point are left out.
+    pub fn last_values(
+        &mut self,
+        paths: Vec<String>,
+        since_time: i64,
+    ) -> anyhow::Result<Vec<LastValue>> {
+        self.last_values_with(paths, since_time, QueryOptions::default())
+    }
+
+    /// Latest point of every path with per-call options, see [`QueryOptions`]
+    pub fn last_values_with(
+        &mut self,
+        paths: Vec<String>,
+        since_time: i64,
+        options: QueryOptions,
+    ) -> anyhow::Result<Vec<LastValue>> {
+        let req = TSLastDataQueryReq::new(
+            self.session_id,
+            paths,
+            options.fetch_size_or(&self.config),
+            since_time,
+            self.statement_id,
+            Some(options.enable_redirect_query_or(&self.config)),
+            Some(false),
+            Some(options.timeout_or(&self.config)),
+        );
+        let resp = self.client.borrow_mut().execute_last_data_query(req)?;
+        if self.is_success(&resp.status) {
+            self.data_set("", resp, &options)?
+                .map(|row| Ok(LastValue::from_row(&row?)?))
+                .collect()
         } else {
             error!(
                 "{}",
@@ -1222,21 +1554,32 @@ impl Session {
         paths: Vec<String>,
         start_time: i64,
         end_time: i64,
+    ) -> anyhow::Result<DataSet> {
+        self.exec_raw_data_query_with(paths, start_time, end_time, QueryOptions::default())
+    }
+
+    /// Execute a raw data query with per-call options, see [`QueryOptions`]
+    pub fn exec_raw_data_query_with(
+        &mut self,
+        paths: Vec<String>,
+        start_time: i64,
+        end_time: i64,
+        options: QueryOptions,
     ) -> anyhow::Result<DataSet> {
         let req = TSRawDataQueryReq::new(
             self.session_id,
             paths,
-            self.config.fetch_size,
+            options.fetch_size_or(&self.config),
             start_time,
             end_time,
             self.statement_id,
-            Some(self.config.enable_redirect_query),
+            Some(options.enable_redirect_query_or(&self.config)),
             Some(false),
-            Some(self.config.timeout),
+            Some(options.timeout_or(&self.config)),
         );
-        let resp = self.client.execute_raw_data_query(req)?;
+        let resp = self.client.borrow_mut().execute_raw_data_query(req)?;
         if self.is_success(&resp.status) {
-            Ok(DataSet::new(resp))
+            self.data_set("", resp, &options)
         } else {
             error!(
                 "{}",
@@ -1252,7 +1595,7 @@ impl Session {
     /// Set time zone
     pub fn set_time_zone(&mut self, time_zone: &str) -> anyhow::Result<()> {
         let req = TSSetTimeZoneReq::new(self.session_id, time_zone.to_string());
-        let status = self.client.set_time_zone(req)?;
+        let status = self.client.borrow_mut().set_time_zone(req)?;
         if !self.is_success(&status) {
             error!(
                 "{}",
@@ -1260,13 +1603,15 @@ impl Session {
             );
             bail!(status.message.unwrap_or_else(|| "None".to_string()))
         } else {
+            self.config.time_zone = time_zone.to_string();
+            self.time_zone = Self::parse_time_zone(time_zone);
             Ok(())
         }
     }
 
     /// Get time zone
     pub fn time_zone(&mut self) -> anyhow::Result<String> {
-        let resp = self.client.get_time_zone(self.session_id)?;
+        let resp = self.client.borrow_mut().get_time_zone(self.session_id)?;
         if self.is_success(&resp.status) {
             Ok(resp.time_zone)
         } else {
@@ -1280,7 +1625,7 @@ impl Session {
 
     /// Get Server properties
     pub fn get_properties(&mut self) -> anyhow::Result<ServerProperties> {
-        match self.client.get_properties() {
+        match self.client.borrow_mut().get_properties() {
             Ok(properties) => Ok(properties),
             Err(error) => {
                 bail!(error)
@@ -1288,16 +1633,66 @@ impl Session {
         }
     }
 
+    fn statement_req(&self, statement: &str, options: &QueryOptions) -> TSExecuteStatementReq {
+        TSExecuteStatementReq::new(
+            self.session_id,
+            statement.to_string(),
+            self.statement_id,
+            options.fetch_size_or(&self.config),
+            options.timeout_or(&self.config),
+            options.enable_redirect_query_or(&self.config),
+            false,
+        )
+    }
+
+    /// Wrap the statement response into a DataSet bound to this session
+    fn data_set(
+        &self,
+        statement: &str,
+        resp: TSExecuteStatementResp,
+        options: &QueryOptions,
+    ) -> anyhow::Result<DataSet> {
+        let cursor = self.query_cursor(statement, &resp, options);
+        DataSet::new(
+            resp,
+            cursor,
+            self.time_zone,
+            self.config.time_format.as_deref(),
+            self.timestamp_precision,
+        )
+    }
+
+    /// Build the server side cursor of a query result, it's used to fetch the remaining pages
+    fn query_cursor(
+        &self,
+        statement: &str,
+        resp: &TSExecuteStatementResp,
+        options: &QueryOptions,
+    ) -> Option<QueryCursor> {
+        match (resp.query_id, &resp.columns) {
+            (Some(query_id), Some(_)) => Some(QueryCursor::new(
+                Rc::clone(&self.client),
+                self.session_id,
+                self.statement_id,
+                query_id,
+                statement,
+                options.fetch_size_or(&self.config),
+                options.timeout_or(&self.config),
+                CancelToken::new(self.config.clone(), query_id, self.statement_id),
+            )),
+            _ => None,
+        }
+    }
+
     /// Verify success status of operation
     fn is_success(&self, status: &TSStatus) -> bool {
         status.code == SUCCESS_CODE
     }
 
-    /// Cancel operation
-    #[allow(dead_code)]
-    fn cancel_operation(&mut self, query_id: i64) -> anyhow::Result<()> {
+    /// Cancel the query `query_id`, see [`CancelToken`] to cancel it while the session is busy
+    pub fn cancel_operation(&mut self, query_id: i64) -> anyhow::Result<()> {
         let req = TSCancelOperationReq::new(self.session_id, query_id);
-        let status = self.client.cancel_operation(req)?;
+        let status = self.client.borrow_mut().cancel_operation(req)?;
         if !self.is_success(&status) {
             let msg = format!("Cancel operation failed,'{:?}'", query_id);
             error!("{}", msg);
@@ -1306,4 +1701,142 @@ impl Session {
             Ok(())
         }
     }
+
+    /// Release the resources of the query `query_id` on the server
+    pub(crate) fn close_operation(
+        &mut self,
+        query_id: i64,
+        statement_id: i64,
+    ) -> anyhow::Result<()> {
+        let req = TSCloseOperationReq::new(self.session_id, query_id, statement_id);
+        let status = self.client.borrow_mut().close_operation(req)?;
+        if !self.is_success(&status) {
+            let msg = format!("Close operation failed,'{:?}'", query_id);
+            error!("{}", msg);
+            bail!(status.message.unwrap_or_else(|| "None".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    /// Bytes the client sends, the server never replies
+    #[derive(Clone, Default)]
+    struct Sent(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for Sent {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Sent {
+        /// Arguments of the only sent call, the request struct is its first field
+        fn request<T>(&self, read: fn(&mut dyn TInputProtocol) -> thrift::Result<T>) -> T {
+            let bytes = self.0.borrow().clone();
+            let mut i_prot = TBinaryInputProtocol::new(io::Cursor::new(bytes), true);
+            i_prot.read_message_begin().unwrap();
+            i_prot.read_struct_begin().unwrap();
+            i_prot.read_field_begin().unwrap();
+            read(&mut i_prot).unwrap()
+        }
+    }
+
+    fn session(sent: &Sent) -> Session {
+        let i_prot = TBinaryInputProtocol::new(io::Cursor::new(vec![]), true);
+        let o_prot = TBinaryOutputProtocol::new(sent.clone(), true);
+        Session {
+            client: Rc::new(RefCell::new(ClientType::new(
+                Box::new(i_prot),
+                Box::new(o_prot),
+            ))),
+            config: Config::default(),
+            session_id: 1,
+            statement_id: 2,
+            timestamp_precision: TimestampPrecision::default(),
+            time_aggregations: None,
+            time_zone: SessionTimeZone::default(),
+            is_close: false,
+        }
+    }
+
+    /// Run `call` against a server which never replies and return the statement request
+    fn statement_req(call: impl FnOnce(&mut Session) -> bool) -> TSExecuteStatementReq {
+        let sent = Sent::default();
+        assert!(call(&mut session(&sent)));
+        sent.request(TSExecuteStatementReq::read_from_in_protocol)
+    }
+
+    #[test]
+    fn query_options_reach_every_request() {
+        let options = || QueryOptions::new().timeout(1500).fetch_size(64);
+        let query = Query::new().from(&["root", "sg", "d1"]);
+        let aggregate =
+            AggregateQuery::new(&["root", "sg", "d1"]).select(Aggregation::Count, &["s1"]);
+        let statement = Statement::new("SELECT s1 FROM root.sg.d1").unwrap();
+        let requests = [
+            statement_req(|session| session.sql_with("FLUSH", options()).is_err()),
+            statement_req(|session| session.query_with("SELECT * FROM root", options()).is_err()),
+            statement_req(|session| session.update_with("FLUSH", options()).is_err()),
+            statement_req(|session| {
+                session
+                    .check_time_series_exists_with("root.sg.d1.s1", options())
+                    .is_err()
+            }),
+            statement_req(|session| session.exec_statement_with(&statement, options()).is_err()),
+            statement_req(|session| session.select_with(&query, options()).is_err()),
+            statement_req(|session| session.aggregate_with(&aggregate, options()).is_err()),
+        ];
+        for req in requests {
+            assert_eq!((req.timeout, req.fetch_size), (Some(1500), Some(64)));
+        }
+
+        let sent = Sent::default();
+        let paths = vec!["root.sg.d1.s1".to_string()];
+        assert!(session(&sent)
+            .last_values_with(paths.clone(), 0, options())
+            .is_err());
+        let req = sent.request(TSLastDataQueryReq::read_from_in_protocol);
+        assert_eq!((req.timeout, req.fetch_size), (Some(1500), Some(64)));
+
+        let sent = Sent::default();
+        assert!(session(&sent)
+            .exec_raw_data_query_with(paths, 0, 1, options())
+            .is_err());
+        let req = sent.request(TSRawDataQueryReq::read_from_in_protocol);
+        assert_eq!((req.timeout, req.fetch_size), (Some(1500), Some(64)));
+    }
+
+    #[test]
+    fn parse_data_type() {
+        assert_eq!("INT64".parse::<DataType>().unwrap(), DataType::INT64);
+        assert_eq!(DataType::from("TEXT"), DataType::TEXT);
+        assert!(matches!(
+            "VECTOR".parse::<DataType>(),
+            Err(IotDBError::Conversion(_))
+        ));
+    }
+
+    #[test]
+    fn query_options_fall_back_to_config() {
+        let config = ConfigBuilder::new().timeout(3000).fetch_size(1024).build();
+        let options = QueryOptions::new().fetch_size(10_000);
+        assert_eq!(options.fetch_size_or(&config), 10_000);
+        assert_eq!(options.timeout_or(&config), 3000);
+        assert!(!options.enable_redirect_query_or(&config));
+        assert!(QueryOptions::new()
+            .enable_redirect_query(true)
+            .enable_redirect_query_or(&config));
+    }
 }
@@ -0,0 +1,135 @@
+//! Write query results into Parquet files through polars
+
+use std::io;
+
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::Schema as ArrowSchema;
+use arrow2::error::ArrowError;
+use arrow2::io::parquet::write::{write_file, Encoding, RowGroupIterator, Version, WriteOptions};
+use polars::error::PolarsError;
+use polars::prelude::{DataFrame, ParquetCompression};
+
+use crate::errors::IotDBError;
+
+/// Options of [`DataSet::write_parquet`](crate::DataSet::write_parquet)
+#[derive(Clone, Debug)]
+pub struct ParquetOptions {
+    /// Rows per row group, the last group may hold fewer
+    pub row_group_size: usize,
+    pub compression: ParquetCompression,
+    /// Write min/max and null count statistics of the columns
+    pub statistics: bool,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        Self {
+            row_group_size: 64 * 1024,
+            compression: ParquetCompression::Snappy,
+            statistics: true,
+        }
+    }
+}
+
+/// Write `first` and the remaining `frames` into one Parquet file, one row group per frame.
+///
+/// The frames are pulled one at a time, so only one row group is held in memory.
+pub(crate) fn write_frames<W, I>(
+    writer: &mut W,
+    first: DataFrame,
+    frames: I,
+    options: &ParquetOptions,
+) -> Result<(), IotDBError>
+where
+    W: io::Write,
+    I: Iterator<Item = Result<DataFrame, IotDBError>>,
+{
+    let schema = ArrowSchema::from(first.schema().to_arrow().fields);
+    let encodings = vec![Encoding::Plain; schema.fields.len()];
+    let write_options = WriteOptions {
+        write_statistics: options.statistics,
+        compression: options.compression,
+        version: Version::V2,
+    };
+
+    // a failing frame ends the row groups, its error is returned once the file is closed
+    let mut failure = None;
+    let chunks = std::iter::once(Ok(first))
+        .chain(frames)
+        .map_while(|frame| match frame {
+            Ok(frame) => Some(frame),
+            Err(error) => {
+                failure = Some(error);
+                None
+            }
+        })
+        .filter(|frame| frame.height() > 0)
+        .map(|mut frame| {
+            frame.as_single_chunk();
+            Ok::<_, ArrowError>(
+                frame
+                    .iter_chunks()
+                    .next()
+                    .unwrap_or_else(|| Chunk::new(vec![])),
+            )
+        });
+
+    let row_groups = RowGroupIterator::try_new(chunks, &schema, write_options, encodings)
+        .map_err(PolarsError::from)?;
+    let parquet_schema = row_groups.parquet_schema().clone();
+    write_file(
+        writer,
+        row_groups,
+        &schema,
+        parquet_schema,
+        write_options,
+        None,
+    )
+    .map_err(PolarsError::from)?;
+
+    match failure {
+        None => Ok(()),
+        Some(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use polars::prelude::{NamedFrom, ParquetReader, SerReader, Series};
+
+    use super::*;
+
+    fn frame(values: &[Option<i32>]) -> DataFrame {
+        DataFrame::new(vec![Series::new("root.sg.d1.s1", values)]).unwrap()
+    }
+
+    #[test]
+    fn write_frames_as_row_groups() {
+        let frames = vec![Ok(frame(&[Some(2), None])), Ok(frame(&[]))];
+        let mut output = vec![];
+        write_frames(
+            &mut output,
+            frame(&[Some(1)]),
+            frames.into_iter(),
+            &ParquetOptions::default(),
+        )
+        .unwrap();
+
+        let df = ParquetReader::new(Cursor::new(output)).finish().unwrap();
+        assert!(df.frame_equal_missing(&frame(&[Some(1), Some(2), None])));
+    }
+
+    #[test]
+    fn write_frames_returns_frame_error() {
+        let frames = vec![Err(IotDBError::Decode("broken page".to_string()))];
+        let result = write_frames(
+            &mut vec![],
+            frame(&[Some(1)]),
+            frames.into_iter(),
+            &ParquetOptions::default(),
+        );
+        assert!(matches!(result, Err(IotDBError::Decode(_))));
+    }
+}
@@ -0,0 +1,319 @@
+//! Typed SELECT statements with quoted path nodes
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::errors::IotDBError;
+use crate::value::Value;
+
+/// Keywords which can't be used as bare path nodes
+const RESERVED_WORDS: &[&str] = &[
+    "ADD",
+    "ALIGN",
+    "ALL",
+    "AND",
+    "AS",
+    "ASC",
+    "BY",
+    "CREATE",
+    "DELETE",
+    "DESC",
+    "DEVICE",
+    "DEVICES",
+    "DISABLE",
+    "DROP",
+    "FALSE",
+    "FILL",
+    "FROM",
+    "GROUP",
+    "INSERT",
+    "INTO",
+    "LEVEL",
+    "LIKE",
+    "LIMIT",
+    "LINEAR",
+    "NOT",
+    "NULL",
+    "NULLS",
+    "OFFSET",
+    "OR",
+    "ORDER",
+    "PREVIOUS",
+    "PREVIOUSUNTILLAST",
+    "REGEXP",
+    "SELECT",
+    "SET",
+    "SHOW",
+    "SLIMIT",
+    "SOFFSET",
+    "TIME",
+    "TIMESERIES",
+    "TIMESTAMP",
+    "TRUE",
+    "VALUES",
+    "WHERE",
+    "WITHOUT",
+];
+
+/// Quote a path node with backticks unless IoTDB parses it bare, backslashes inside are
+/// escaped and backticks are doubled.
+///
+/// Bare nodes are made of letters, digits and `_` and don't start with a digit, wildcards
+/// are kept as they are. Every other node is quoted, including nodes which contain backticks
+/// already, so a node can't add paths to the statement.
+pub fn quote_node(node: &str) -> Cow<'_, str> {
+    let is_bare = node.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && node.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !RESERVED_WORDS
+            .iter()
+            .any(|word| word.eq_ignore_ascii_case(node));
+    if is_bare || node == "*" || node == "**" || node == "root" {
+        Cow::Borrowed(node)
+    } else {
+        Cow::Owned(format!(
+            "`{}`",
+            node.replace('\\', "\\\\").replace('`', "``")
+        ))
+    }
+}
+
+/// Join the nodes into a path, quoting the nodes which need it, e.g.
+/// `["root", "sg", "dev-01", "temperature"]` becomes ``root.sg.`dev-01`.temperature``
+pub fn join_path<S: AsRef<str>>(nodes: &[S]) -> String {
+    nodes
+        .iter()
+        .map(|node| quote_node(node.as_ref()))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// SQL literal of a value, TEXT is single quoted with backslashes escaped and quotes doubled,
+/// `None` for nulls and non-finite floats
+pub(crate) fn value_literal(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Float(value) if !value.is_finite() => None,
+        Value::Double(value) if !value.is_finite() => None,
+        Value::Text(text) => Some(format!(
+            "'{}'",
+            String::from_utf8_lossy(text)
+                .replace('\\', "\\\\")
+                .replace('\'', "''")
+        )),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Operator of a value filter
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    Eq,
+    NotEq,
+    Gt,
+    GtEq,
+    Lt,
+    LtEq,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparison::Eq => write!(f, "="),
+            Comparison::NotEq => write!(f, "!="),
+            Comparison::Gt => write!(f, ">"),
+            Comparison::GtEq => write!(f, ">="),
+            Comparison::Lt => write!(f, "<"),
+            Comparison::LtEq => write!(f, "<="),
+        }
+    }
+}
+
+/// A SELECT statement built from typed parts, run it with [`Session::select`](crate::Session::select).
+///
+/// Paths are given as nodes, which are quoted when needed.
+///
+/// ```
+/// use iotdb::{Comparison, Query};
+///
+/// let query = Query::new()
+///     .select(&["temperature"])
+///     .from(&["root", "sg", "dev-01"])
+///     .time_range(0, 1000)
+///     .filter(&["temperature"], Comparison::Gt, 36.5)
+///     .order_by_time_desc()
+///     .limit(10, 0);
+/// assert_eq!(
+///     query.to_sql().unwrap(),
+///     "SELECT temperature FROM root.sg.`dev-01` \
+///      WHERE time >= 0 AND time < 1000 AND temperature > 36.5 \
+///      ORDER BY TIME DESC LIMIT 10 OFFSET 0"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    select: Vec<String>,
+    from: Vec<String>,
+    predicates: Vec<String>,
+    /// A filter value which has no SQL literal, the statement can't be built
+    invalid_value: Option<Value>,
+    order_by_time_desc: bool,
+    limit: Option<(usize, usize)>,
+    series_limit: Option<(usize, usize)>,
+    align_by_device: bool,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select the suffix path of the FROM prefixes, all series are selected if unset
+    pub fn select<S: AsRef<str>>(mut self, nodes: &[S]) -> Self {
+        self.select.push(join_path(nodes));
+        self
+    }
+
+    /// Add a FROM prefix path
+    pub fn from<S: AsRef<str>>(mut self, nodes: &[S]) -> Self {
+        self.from.push(join_path(nodes));
+        self
+    }
+
+    /// Only select points in `[start, end)`, in the server precision
+    pub fn time_range(self, start: i64, end: i64) -> Self {
+        self.start_time(start).end_time(end)
+    }
+
+    /// Only select points at or after `start`
+    pub fn start_time(mut self, start: i64) -> Self {
+        self.predicates.push(format!("time >= {}", start));
+        self
+    }
+
+    /// Only select points before `end`
+    pub fn end_time(mut self, end: i64) -> Self {
+        self.predicates.push(format!("time < {}", end));
+        self
+    }
+
+    /// Only select rows where the series at the suffix path compares to `value`,
+    /// all filters must hold
+    pub fn filter<S: AsRef<str>, V: Into<Value>>(
+        mut self,
+        nodes: &[S],
+        comparison: Comparison,
+        value: V,
+    ) -> Self {
+        let value = value.into();
+        match value_literal(&value) {
+            None => self.invalid_value = Some(value),
+            Some(literal) => {
+                self.predicates
+                    .push(format!("{} {} {}", join_path(nodes), comparison, literal))
+            }
+        }
+        self
+    }
+
+    pub fn order_by_time_desc(mut self) -> Self {
+        self.order_by_time_desc = true;
+        self
+    }
+
+    /// `LIMIT` and `OFFSET` of the rows
+    pub fn limit(mut self, limit: usize, offset: usize) -> Self {
+        self.limit = Some((limit, offset));
+        self
+    }
+
+    /// `SLIMIT` and `SOFFSET` of the series
+    pub fn series_limit(mut self, limit: usize, offset: usize) -> Self {
+        self.series_limit = Some((limit, offset));
+        self
+    }
+
+    pub fn align_by_device(mut self) -> Self {
+        self.align_by_device = true;
+        self
+    }
+
+    /// Build the statement, a query without a FROM prefix or with a null filter value is rejected
+    pub fn to_sql(&self) -> Result<String, IotDBError> {
+        if self.from.is_empty() {
+            return Err(IotDBError::Conversion(
+                "query has no FROM prefix path".to_string(),
+            ));
+        }
+        if let Some(value) = &self.invalid_value {
+            return Err(IotDBError::Conversion(format!(
+                "{:?} can't be compared in a filter",
+                value
+            )));
+        }
+
+        let select = match self.select.is_empty() {
+            true => "*".to_string(),
+            false => self.select.join(", "),
+        };
+        let mut sql = format!("SELECT {} FROM {}", select, self.from.join(", "));
+        if !self.predicates.is_empty() {
+            sql.push_str(&format!(" WHERE {}", self.predicates.join(" AND ")));
+        }
+        if self.order_by_time_desc {
+            sql.push_str(" ORDER BY TIME DESC");
+        }
+        if let Some((limit, offset)) = self.limit {
+            sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+        }
+        if let Some((limit, offset)) = self.series_limit {
+            sql.push_str(&format!(" SLIMIT {} SOFFSET {}", limit, offset));
+        }
+        if self.align_by_device {
+            sql.push_str(" ALIGN BY DEVICE");
+        }
+        Ok(sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_nodes_which_need_it() {
+        assert_eq!(
+            join_path(&["root", "sg", "dev-01", "01", "a.b", "time", "s_1", "*"]),
+            "root.sg.`dev-01`.`01`.`a.b`.`time`.s_1.*"
+        );
+        assert_eq!(quote_node("a`b"), "`a``b`");
+        assert_eq!(quote_node("`x`"), "```x```");
+        assert_eq!(
+            quote_node("`a`, root.secret.`b`"),
+            "```a``, root.secret.``b```"
+        );
+        assert_eq!(quote_node("a\\"), "`a\\\\`");
+        assert_eq!(quote_node("温度"), "温度");
+    }
+
+    #[test]
+    fn query_to_sql() {
+        let query = Query::new()
+            .select(&["s1"])
+            .select(&["status"])
+            .from(&["root", "sg", "*"])
+            .filter(&["status"], Comparison::NotEq, "it's off")
+            .series_limit(5, 1)
+            .align_by_device();
+        assert_eq!(
+            query.to_sql().unwrap(),
+            "SELECT s1, status FROM root.sg.* WHERE status != 'it''s off' \
+             SLIMIT 5 SOFFSET 1 ALIGN BY DEVICE"
+        );
+
+        assert!(Query::new().select(&["s1"]).to_sql().is_err());
+        let query = Query::new()
+            .from(&["root", "sg"])
+            .filter(&["s1"], Comparison::Eq, f64::NAN);
+        assert!(query.to_sql().is_err());
+    }
+}
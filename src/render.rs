@@ -0,0 +1,299 @@
+//! Render query results as text tables
+
+use std::fmt::{self, Write as _};
+use std::ops::Range;
+
+use prettytable::Row as PrettyRow;
+use prettytable::{Cell, Table};
+
+use crate::ds::{ColumnValues, Field, NonAlignColumn, RecordBatch};
+use crate::time_zone::SessionTimeZone;
+use crate::timestamp::{Timestamp, TimestampPrecision};
+use crate::DataType;
+
+/// How TEXT values which aren't valid UTF-8 are rendered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// Lowercase hex digits with a `0x` prefix, e.g. `0xff00`
+    #[default]
+    Hex,
+    /// Invalid sequences are replaced with `U+FFFD`
+    Lossy,
+}
+
+/// Options of [`DataSet::render`](crate::DataSet::render)
+#[derive(Clone, Debug)]
+pub struct TableOptions {
+    /// Rows rendered at most, the first and the last rows are kept around a `...` row
+    pub max_rows: Option<usize>,
+    /// Characters of a value at most, longer values are cut and end with `…`
+    pub max_column_width: Option<usize>,
+    /// Rendered for null values
+    pub null_value: String,
+    pub binary_format: BinaryFormat,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            max_rows: None,
+            max_column_width: None,
+            null_value: "null".to_string(),
+            binary_format: BinaryFormat::default(),
+        }
+    }
+}
+
+/// Renders values and timestamps into table cells
+pub(crate) struct TableRenderer<'a> {
+    pub(crate) options: &'a TableOptions,
+    pub(crate) time_zone: &'a SessionTimeZone,
+    pub(crate) time_format: Option<&'a str>,
+}
+
+impl TableRenderer<'_> {
+    /// Table of the unconsumed rows of an aligned batch
+    pub(crate) fn batch_table(&self, batch: &RecordBatch, with_time: bool) -> Table {
+        let mut table = new_table();
+        if batch.columns.is_empty() {
+            return table;
+        }
+
+        let mut titles = Vec::with_capacity(batch.columns.len() + 1);
+        if with_time {
+            titles.push(cell!("Time"));
+        }
+        batch
+            .columns
+            .iter()
+            .for_each(|column| titles.push(cell!(column)));
+        let width = titles.len();
+        table.set_titles(PrettyRow::new(titles));
+
+        self.add_rows(
+            &mut table,
+            batch.timestamps.len() - batch.offset,
+            width,
+            |index| {
+                let row = batch.offset + index;
+                let mut cells = Vec::with_capacity(width);
+                if with_time {
+                    cells.push(
+                        self.time_cell(Timestamp::new(batch.timestamps[row], batch.precision)),
+                    );
+                }
+                batch.values.iter().for_each(|values| {
+                    cells.push(self.text_cell(values_text(values, row, self.options)))
+                });
+                cells
+            },
+        );
+        table
+    }
+
+    /// Table of `Time` and value column pairs, shorter columns are padded with empty cells
+    pub(crate) fn non_align_table(
+        &self,
+        columns: &[NonAlignColumn],
+        precision: TimestampPrecision,
+    ) -> Table {
+        let mut table = new_table();
+        let mut titles = Vec::with_capacity(columns.len() * 2);
+        columns.iter().for_each(|column| {
+            titles.push(cell!("Time"));
+            titles.push(cell!(column.name()));
+        });
+        let width = titles.len();
+        table.set_titles(PrettyRow::new(titles));
+
+        let height = columns.iter().map(NonAlignColumn::len).max().unwrap_or(0);
+        self.add_rows(&mut table, height, width, |row| {
+            let mut cells = Vec::with_capacity(width);
+            columns.iter().for_each(|column| {
+                match (column.timestamps().get(row), column.fields().get(row)) {
+                    (Some(timestamp), Some(field)) => {
+                        cells.push(self.time_cell(Timestamp::new(*timestamp, precision)));
+                        cells.push(self.text_cell(field_text(field, self.options)));
+                    }
+                    _ => {
+                        cells.push(cell!(""));
+                        cells.push(cell!(""));
+                    }
+                }
+            });
+            cells
+        });
+        table
+    }
+
+    /// Add the rows which fit into `max_rows`, elided rows are replaced by one `...` row
+    fn add_rows(
+        &self,
+        table: &mut Table,
+        height: usize,
+        width: usize,
+        mut row: impl FnMut(usize) -> Vec<Cell>,
+    ) {
+        let (head, tail) = visible_rows(height, self.options.max_rows);
+        head.for_each(|index| {
+            table.add_row(PrettyRow::new(row(index)));
+        });
+        if let Some(tail) = tail {
+            table.add_row(PrettyRow::new(vec![cell!("..."); width]));
+            tail.for_each(|index| {
+                table.add_row(PrettyRow::new(row(index)));
+            });
+        }
+    }
+
+    fn time_cell(&self, timestamp: Timestamp) -> Cell {
+        cell!(self.time_zone.format(timestamp, self.time_format))
+    }
+
+    fn text_cell(&self, text: String) -> Cell {
+        match self.options.max_column_width {
+            Some(width) if text.chars().count() > width => {
+                let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+                cut.push('…');
+                cell!(cut)
+            }
+            _ => cell!(text),
+        }
+    }
+}
+
+fn new_table() -> Table {
+    let mut table = Table::new();
+    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table
+}
+
+/// Rows before the elision and, if rows are elided, the rows after it
+fn visible_rows(height: usize, max_rows: Option<usize>) -> (Range<usize>, Option<Range<usize>>) {
+    match max_rows {
+        Some(max_rows) if height > max_rows => {
+            let head = max_rows - max_rows / 2;
+            (0..head, Some(height - max_rows / 2..height))
+        }
+        _ => (0..height, None),
+    }
+}
+
+fn values_text(values: &ColumnValues, row: usize, options: &TableOptions) -> String {
+    match values {
+        ColumnValues::Boolean(values) => display_text(values[row], options),
+        ColumnValues::Int32(values) => display_text(values[row], options),
+        ColumnValues::Int64(values) => display_text(values[row], options),
+        ColumnValues::Float(values) => display_text(values[row], options),
+        ColumnValues::Double(values) => display_text(values[row], options),
+        ColumnValues::Text { data, ranges } => {
+            binary_text(ranges[row].map(|(start, end)| &data[start..end]), options)
+        }
+    }
+}
+
+fn field_text(field: &Field, options: &TableOptions) -> String {
+    match field.data_type() {
+        DataType::BOOLEAN => display_text(field.bool_value, options),
+        DataType::INT32 => display_text(field.int_value, options),
+        DataType::INT64 => display_text(field.long_value, options),
+        DataType::FLOAT => display_text(field.float_value, options),
+        DataType::DOUBLE => display_text(field.double_value, options),
+        DataType::TEXT => binary_text(field.binary_value.as_deref(), options),
+    }
+}
+
+fn display_text<T: fmt::Display>(value: Option<T>, options: &TableOptions) -> String {
+    match value {
+        None => options.null_value.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn binary_text(value: Option<&[u8]>, options: &TableOptions) -> String {
+    let value = match value {
+        None => return options.null_value.clone(),
+        Some(value) => value,
+    };
+    match (std::str::from_utf8(value), options.binary_format) {
+        (Ok(text), _) => text.to_string(),
+        (Err(_), BinaryFormat::Lossy) => String::from_utf8_lossy(value).to_string(),
+        (Err(_), BinaryFormat::Hex) => {
+            let mut text = String::with_capacity(value.len() * 2 + 2);
+            text.push_str("0x");
+            value
+                .iter()
+                .for_each(|byte| write!(text, "{:02x}", byte).unwrap());
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn render(batch: &RecordBatch, options: &TableOptions) -> Vec<String> {
+        let renderer = TableRenderer {
+            options,
+            time_zone: &SessionTimeZone::default(),
+            time_format: Some("%H:%M:%S"),
+        };
+        renderer
+            .batch_table(batch, true)
+            .to_string()
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect()
+    }
+
+    #[test]
+    fn render_elides_rows_and_formats_values() {
+        let text = b"abcdef\xffzz";
+        let batch = RecordBatch {
+            columns: Arc::new(vec!["s1".to_string(), "s2".to_string()]),
+            timestamps: vec![0, 1000, 2000, 3000, 4000],
+            values: vec![
+                ColumnValues::Double(vec![Some(f64::NAN), None, Some(2.5), None, Some(4.0)]),
+                ColumnValues::Text {
+                    data: text.to_vec(),
+                    ranges: vec![Some((0, 6)), None, None, Some((0, 2)), Some((6, 9))],
+                },
+            ],
+            offset: 1,
+            ..RecordBatch::default()
+        };
+        let options = TableOptions {
+            max_rows: Some(3),
+            max_column_width: Some(4),
+            null_value: "-".to_string(),
+            ..TableOptions::default()
+        };
+
+        assert_eq!(
+            render(&batch, &options)[3..7],
+            [
+                "| 00:00:01 | - | - |",
+                "| 00:00:02 | 2.5 | - |",
+                "| ... | ... | ... |",
+                "| 00:00:04 | 4 | 0xf… |",
+            ]
+        );
+
+        let options = TableOptions {
+            binary_format: BinaryFormat::Lossy,
+            ..TableOptions::default()
+        };
+        assert_eq!(render(&batch, &options)[6], "| 00:00:04 | 4 | �zz |");
+    }
+
+    #[test]
+    fn visible_rows_keep_head_and_tail() {
+        assert_eq!(visible_rows(3, None), (0..3, None));
+        assert_eq!(visible_rows(3, Some(3)), (0..3, None));
+        assert_eq!(visible_rows(10, Some(3)), (0..2, Some(9..10)));
+        assert_eq!(visible_rows(10, Some(0)), (0..0, Some(10..10)));
+    }
+}
@@ -0,0 +1,70 @@
+//! Column metadata of query results
+
+use crate::DataType;
+
+/// Metadata of one result column
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: DataType,
+    /// Storage group of the time series, `None` for computed columns
+    pub storage_group: Option<String>,
+    /// Whether the column name is an alias given with `AS`
+    pub is_alias: bool,
+    /// Index of the column in the value buffers sent by the server
+    pub index: usize,
+}
+
+/// Metadata of a query result, available before any row is read
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schema {
+    /// Kind of the executed statement, e.g. `query` or `showTimeseries`
+    pub operation_type: Option<String>,
+    pub columns: Vec<ColumnSchema>,
+}
+
+impl Schema {
+    pub(crate) fn new(
+        operation_type: Option<String>,
+        columns: &[String],
+        data_types: &[DataType],
+        column_indexes: &[usize],
+        sg_columns: Option<Vec<String>>,
+        alias_columns: Option<Vec<i8>>,
+    ) -> Self {
+        let sg_columns = sg_columns.unwrap_or_default();
+        let alias_columns = alias_columns.unwrap_or_default();
+        let columns = columns
+            .iter()
+            .zip(data_types)
+            .zip(column_indexes)
+            .enumerate()
+            .map(|(i, ((name, data_type), index))| ColumnSchema {
+                name: name.clone(),
+                data_type: *data_type,
+                storage_group: sg_columns
+                    .get(i)
+                    .filter(|storage_group| !storage_group.is_empty())
+                    .cloned(),
+                is_alias: alias_columns.get(i).is_some_and(|alias| *alias == 1),
+                index: *index,
+            })
+            .collect();
+        Self {
+            operation_type,
+            columns,
+        }
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+}
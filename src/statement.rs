@@ -0,0 +1,365 @@
+//! Statements with placeholders bound on the client
+
+use std::collections::BTreeMap;
+
+use crate::errors::IotDBError;
+use crate::query::{join_path, value_literal};
+use crate::timestamp::{Timestamp, TimestampPrecision};
+use crate::value::Value;
+
+/// Keywords after which placeholders bind paths
+const PATH_CLAUSES: &[&str] = &["SELECT", "FROM", "INTO", "TIMESERIES", "DEVICES"];
+
+/// Keywords after which placeholders bind values
+const VALUE_CLAUSES: &[&str] = &[
+    "WHERE", "VALUES", "GROUP", "FILL", "HAVING", "ORDER", "LIMIT", "OFFSET", "SLIMIT", "SOFFSET",
+];
+
+/// A parameter bound to a placeholder of a [`Statement`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Param {
+    /// A literal, TEXT is single quoted with quotes inside doubled
+    Value(Value),
+    /// A timestamp, converted into the precision of the server
+    Time(Timestamp),
+    /// Path nodes, quoted with backticks when needed, see [`join_path`]
+    Path(Vec<String>),
+}
+
+impl Param {
+    pub fn path<S: AsRef<str>>(nodes: &[S]) -> Self {
+        Param::Path(nodes.iter().map(|node| node.as_ref().to_string()).collect())
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Param::Value(value) => match value.data_type() {
+                None => "null".to_string(),
+                Some(data_type) => format!("{:?} value {}", data_type, value),
+            },
+            Param::Time(_) => "timestamp".to_string(),
+            Param::Path(nodes) if nodes.is_empty() => "empty path".to_string(),
+            Param::Path(_) => "path".to_string(),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<T> for Param {
+    fn from(value: T) -> Self {
+        Param::Value(value.into())
+    }
+}
+
+impl From<Timestamp> for Param {
+    fn from(timestamp: Timestamp) -> Self {
+        Param::Time(timestamp)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Kind {
+    Value,
+    Path,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    /// `None` name for `?`
+    Placeholder {
+        name: Option<String>,
+        kind: Kind,
+    },
+}
+
+/// A statement with `?` or `:name` placeholders, the parameters are bound on the client so
+/// untrusted values can't change the statement. Run it with
+/// [`Session::exec_statement`](crate::Session::exec_statement).
+///
+/// Placeholders in the `SELECT`, `FROM` and `INTO` clauses or next to a `.` bind [`Param::Path`],
+/// all others bind [`Param::Value`] or [`Param::Time`]. Placeholders inside quotes are kept as
+/// they are.
+///
+/// ```
+/// use iotdb::{Param, Statement, Timestamp, TimestampPrecision};
+///
+/// let statement = Statement::new("SELECT ? FROM root.sg.? WHERE time >= ? AND status = ?")
+///     .unwrap()
+///     .bind(Param::path(&["temperature"]))
+///     .bind(Param::path(&["dev-01"]))
+///     .bind(Timestamp::new(1_000_000, TimestampPrecision::Microsecond))
+///     .bind("it's on");
+/// assert_eq!(
+///     statement.to_sql(TimestampPrecision::Millisecond).unwrap(),
+///     "SELECT temperature FROM root.sg.`dev-01` WHERE time >= 1000 AND status = 'it''s on'"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    segments: Vec<Segment>,
+    positional: Vec<Param>,
+    named: BTreeMap<String, Param>,
+}
+
+impl Statement {
+    /// Parse the placeholders of the statement, mixing `?` and named placeholders is rejected
+    pub fn new(sql: &str) -> Result<Self, IotDBError> {
+        let chars: Vec<char> = sql.chars().collect();
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut path_clause = false;
+        let mut index = 0;
+        while index < chars.len() {
+            let (name, end) = match chars[index] {
+                '\'' | '"' | '`' => {
+                    let end = quoted_end(&chars, index);
+                    text.extend(&chars[index..end]);
+                    index = end;
+                    continue;
+                }
+                '?' => (None, index + 1),
+                ':' if chars.get(index + 1).copied().is_some_and(is_word_start) => {
+                    let end = word_end(&chars, index + 1);
+                    (Some(chars[index + 1..end].iter().collect()), end)
+                }
+                c if is_word_start(c) => {
+                    let end = word_end(&chars, index);
+                    let word: String = chars[index..end].iter().collect();
+                    if PATH_CLAUSES.iter().any(|w| w.eq_ignore_ascii_case(&word)) {
+                        path_clause = true;
+                    } else if VALUE_CLAUSES.iter().any(|w| w.eq_ignore_ascii_case(&word)) {
+                        path_clause = false;
+                    }
+                    text.push_str(&word);
+                    index = end;
+                    continue;
+                }
+                c => {
+                    text.push(c);
+                    index += 1;
+                    continue;
+                }
+            };
+
+            let is_path = path_clause
+                || (index > 0 && chars[index - 1] == '.')
+                || chars.get(end) == Some(&'.');
+            let kind = match is_path {
+                true => Kind::Path,
+                false => Kind::Value,
+            };
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Placeholder { name, kind });
+            index = end;
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        let mut placeholders = segments.iter().filter_map(|segment| match segment {
+            Segment::Placeholder { name, .. } => Some(name.is_some()),
+            Segment::Text(_) => None,
+        });
+        if let Some(named) = placeholders.next() {
+            if placeholders.any(|other| other != named) {
+                return Err(IotDBError::Conversion(
+                    "statement mixes `?` and named placeholders".to_string(),
+                ));
+            }
+        }
+
+        Ok(Self {
+            segments,
+            positional: Vec::new(),
+            named: BTreeMap::new(),
+        })
+    }
+
+    /// Bind the next `?` placeholder
+    pub fn bind(mut self, param: impl Into<Param>) -> Self {
+        self.positional.push(param.into());
+        self
+    }
+
+    /// Bind all `:name` placeholders, the name is given without the colon
+    pub fn bind_named(mut self, name: &str, param: impl Into<Param>) -> Self {
+        self.named.insert(name.to_string(), param.into());
+        self
+    }
+
+    /// Build the statement, timestamps are converted into `precision`.
+    ///
+    /// Fails with [`IotDBError::MissingParameter`] for an unbound placeholder,
+    /// [`IotDBError::UnknownParameter`] for a parameter without a placeholder and
+    /// [`IotDBError::ParameterKind`] for a parameter which doesn't fit its placeholder.
+    pub fn to_sql(&self, precision: TimestampPrecision) -> Result<String, IotDBError> {
+        let mut sql = String::new();
+        let mut position = 0;
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => sql.push_str(text),
+                Segment::Placeholder { name, kind } => {
+                    let (label, param) = match name {
+                        None => {
+                            position += 1;
+                            (format!("?{}", position), self.positional.get(position - 1))
+                        }
+                        Some(name) => (format!(":{}", name), self.named.get(name)),
+                    };
+                    match param {
+                        None => return Err(IotDBError::MissingParameter(label)),
+                        Some(param) => sql.push_str(&param_sql(&label, param, *kind, precision)?),
+                    }
+                }
+            }
+        }
+
+        if self.positional.len() > position {
+            return Err(IotDBError::UnknownParameter(format!("?{}", position + 1)));
+        }
+        if let Some(name) = self.named.keys().find(|name| {
+            !self.segments.iter().any(|segment| {
+                matches!(segment, Segment::Placeholder { name: Some(other), .. } if other == *name)
+            })
+        }) {
+            return Err(IotDBError::UnknownParameter(format!(":{}", name)));
+        }
+        Ok(sql)
+    }
+}
+
+fn param_sql(
+    label: &str,
+    param: &Param,
+    kind: Kind,
+    precision: TimestampPrecision,
+) -> Result<String, IotDBError> {
+    let kind_error = |expected: &str| IotDBError::ParameterKind {
+        name: label.to_string(),
+        expected: expected.to_string(),
+        found: param.describe(),
+    };
+    match (kind, param) {
+        (Kind::Path, Param::Path(nodes)) if !nodes.is_empty() => Ok(join_path(nodes)),
+        (Kind::Path, _) => Err(kind_error("path")),
+        (Kind::Value, Param::Value(value)) => {
+            value_literal(value).ok_or_else(|| kind_error("value with a literal"))
+        }
+        (Kind::Value, Param::Time(timestamp)) => timestamp
+            .to_precision(precision)
+            .map(|timestamp| timestamp.value().to_string())
+            .ok_or_else(|| {
+                IotDBError::Conversion(format!(
+                    "{} is out of range of {} timestamps",
+                    timestamp, precision
+                ))
+            }),
+        (Kind::Value, Param::Path(_)) => Err(kind_error("value or timestamp")),
+    }
+}
+
+fn is_word_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn word_end(chars: &[char], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .position(|c| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(chars.len(), |len| start + len)
+}
+
+/// End of the quoted part starting at `start`, doubled quotes don't end it
+fn quoted_end(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let mut index = start + 1;
+    while index < chars.len() {
+        if chars[index] == quote {
+            if chars.get(index + 1) == Some(&quote) {
+                index += 2;
+                continue;
+            }
+            return index + 1;
+        }
+        index += 1;
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_named_params() {
+        let statement = Statement::new(
+            "INSERT INTO root.sg.:device(timestamp, :measurement) VALUES (:time, :value)",
+        )
+        .unwrap()
+        .bind_named("device", Param::path(&["dev 1"]))
+        .bind_named("measurement", Param::path(&["status"]))
+        .bind_named(
+            "time",
+            Timestamp::new(1_500, TimestampPrecision::Microsecond),
+        )
+        .bind_named("value", "'; DROP");
+        assert_eq!(
+            statement.to_sql(TimestampPrecision::Millisecond).unwrap(),
+            "INSERT INTO root.sg.`dev 1`(timestamp, status) VALUES (1, '''; DROP')"
+        );
+
+        let statement = Statement::new("SELECT s1 FROM root.sg WHERE s2 = '?:x' AND s1 > :x")
+            .unwrap()
+            .bind_named("x", 1);
+        assert_eq!(
+            statement.to_sql(TimestampPrecision::Millisecond).unwrap(),
+            "SELECT s1 FROM root.sg WHERE s2 = '?:x' AND s1 > 1"
+        );
+    }
+
+    #[test]
+    fn bound_params_stay_inside_their_quotes() {
+        let statement = Statement::new("SELECT :s FROM root.sg.:device WHERE s1 = :text")
+            .unwrap()
+            .bind_named("s", Param::path(&["`s1`, root.secret.`s2`"]))
+            .bind_named("device", Param::path(&["d1\\"]))
+            .bind_named("text", "it's \\");
+        assert_eq!(
+            statement.to_sql(TimestampPrecision::Millisecond).unwrap(),
+            "SELECT ```s1``, root.secret.``s2``` FROM root.sg.`d1\\\\` WHERE s1 = 'it''s \\\\'"
+        );
+    }
+
+    #[test]
+    fn binding_errors() {
+        let precision = TimestampPrecision::Millisecond;
+        let statement = Statement::new("SELECT * FROM root.sg.? WHERE s1 > ?").unwrap();
+        assert!(matches!(
+            statement.clone().bind(Param::path(&["d1"])).to_sql(precision),
+            Err(IotDBError::MissingParameter(name)) if name == "?2"
+        ));
+        assert!(matches!(
+            statement.clone().bind(1).bind(2).to_sql(precision),
+            Err(IotDBError::ParameterKind { name, .. }) if name == "?1"
+        ));
+        assert!(matches!(
+            statement
+                .clone()
+                .bind(Param::path(&["d1"]))
+                .bind(Value::Null)
+                .to_sql(precision),
+            Err(IotDBError::ParameterKind { found, .. }) if found == "null"
+        ));
+        assert!(matches!(
+            statement
+                .bind(Param::path(&["d1"]))
+                .bind(1)
+                .bind(2)
+                .to_sql(precision),
+            Err(IotDBError::UnknownParameter(name)) if name == "?3"
+        ));
+        assert!(Statement::new("SELECT * FROM root.sg WHERE s1 > ? AND s2 < :x").is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Statistics and time checks of query results
+
+use std::time::Duration;
+
+use crate::ds::ColumnValues;
+use crate::timestamp::{Timestamp, TimestampPrecision};
+use crate::value::Value;
+use crate::DataType;
+
+/// Statistics of one column, see [`DataSet::stats`](crate::DataSet::stats)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnStats {
+    pub name: String,
+    pub data_type: DataType,
+    /// Number of non-null values
+    pub count: usize,
+    pub null_count: usize,
+    /// Smallest non-null value, NaN is skipped
+    pub min: Option<Value>,
+    /// Largest non-null value, NaN is skipped
+    pub max: Option<Value>,
+    /// Mean of a numeric column, `None` for BOOLEAN and TEXT
+    pub mean: Option<f64>,
+    /// Population standard deviation of a numeric column, `None` for BOOLEAN and TEXT
+    pub stddev: Option<f64>,
+    /// First non-null point in row order
+    pub first: Option<(Timestamp, Value)>,
+    /// Last non-null point in row order
+    pub last: Option<(Timestamp, Value)>,
+}
+
+impl ColumnStats {
+    /// Statistics of the values from row `offset` on
+    pub(crate) fn new(
+        name: &str,
+        values: &ColumnValues,
+        timestamps: &[i64],
+        offset: usize,
+        precision: TimestampPrecision,
+    ) -> Self {
+        let mut stats = Self {
+            name: name.to_string(),
+            data_type: values.data_type(),
+            count: 0,
+            null_count: 0,
+            min: None,
+            max: None,
+            mean: None,
+            stddev: None,
+            first: None,
+            last: None,
+        };
+        let timestamps = timestamps[offset..]
+            .iter()
+            .map(|timestamp| Timestamp::new(*timestamp, precision));
+        match values {
+            ColumnValues::Boolean(values) => stats.collect(
+                timestamps.zip(values[offset..].iter().copied()),
+                Value::Bool,
+                None,
+            ),
+            ColumnValues::Int32(values) => stats.collect(
+                timestamps.zip(values[offset..].iter().copied()),
+                Value::Int32,
+                Some(|value| value as f64),
+            ),
+            ColumnValues::Int64(values) => stats.collect(
+                timestamps.zip(values[offset..].iter().copied()),
+                Value::Int64,
+                Some(|value| value as f64),
+            ),
+            ColumnValues::Float(values) => stats.collect(
+                timestamps.zip(values[offset..].iter().copied()),
+                Value::Float,
+                Some(|value| value as f64),
+            ),
+            ColumnValues::Double(values) => stats.collect(
+                timestamps.zip(values[offset..].iter().copied()),
+                Value::Double,
+                Some(|value| value),
+            ),
+            ColumnValues::Text { data, ranges } => stats.collect(
+                timestamps.zip(
+                    ranges[offset..]
+                        .iter()
+                        .map(|range| range.map(|(start, end)| &data[start..end])),
+                ),
+                |value| Value::Text(value.to_vec()),
+                None,
+            ),
+        }
+        stats
+    }
+
+    /// Walk the points once, the mean and variance are computed with Welford's method
+    fn collect<T: Copy + PartialOrd>(
+        &mut self,
+        points: impl Iterator<Item = (Timestamp, Option<T>)>,
+        to_value: impl Fn(T) -> Value,
+        to_f64: Option<fn(T) -> f64>,
+    ) {
+        let (mut min, mut max): (Option<T>, Option<T>) = (None, None);
+        let (mut first, mut last) = (None, None);
+        let (mut mean, mut m2) = (0.0, 0.0);
+        for (timestamp, value) in points {
+            let value = match value {
+                None => {
+                    self.null_count += 1;
+                    continue;
+                }
+                Some(value) => value,
+            };
+            self.count += 1;
+            first.get_or_insert((timestamp, value));
+            last = Some((timestamp, value));
+            // NaN isn't comparable to itself
+            if value.partial_cmp(&value).is_some() {
+                if min.is_none_or(|min| value < min) {
+                    min = Some(value);
+                }
+                if max.is_none_or(|max| value > max) {
+                    max = Some(value);
+                }
+            }
+            if let Some(to_f64) = to_f64 {
+                let value = to_f64(value);
+                let delta = value - mean;
+                mean += delta / self.count as f64;
+                m2 += delta * (value - mean);
+            }
+        }
+
+        self.min = min.map(&to_value);
+        self.max = max.map(&to_value);
+        self.first = first.map(|(timestamp, value)| (timestamp, to_value(value)));
+        self.last = last.map(|(timestamp, value)| (timestamp, to_value(value)));
+        if to_f64.is_some() && self.count > 0 {
+            self.mean = Some(mean);
+            self.stddev = Some((m2 / self.count as f64).sqrt());
+        }
+    }
+}
+
+/// Consecutive rows which are further apart than the allowed interval
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TimeGap {
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+/// Problems of the timestamps of a result, see [`DataSet::check_time`](crate::DataSet::check_time).
+///
+/// Rows are compared with the previous row, so results of `ORDER BY TIME DESC` queries are
+/// reported as out of order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimeReport {
+    pub gaps: Vec<TimeGap>,
+    /// Timestamps which are equal to the timestamp of the previous row
+    pub duplicates: Vec<Timestamp>,
+    /// `(previous, timestamp)` of rows whose timestamp is before the previous row
+    pub out_of_order: Vec<(Timestamp, Timestamp)>,
+}
+
+impl TimeReport {
+    /// Check the timestamps, gaps are only reported between rows in order
+    pub(crate) fn check(
+        timestamps: &[i64],
+        precision: TimestampPrecision,
+        max_interval: Duration,
+    ) -> Self {
+        let max_interval = duration_units(max_interval, precision);
+        let timestamp = |value| Timestamp::new(value, precision);
+        let mut report = Self::default();
+        for pair in timestamps.windows(2) {
+            let (previous, current) = (pair[0], pair[1]);
+            if current == previous {
+                report.duplicates.push(timestamp(current));
+            } else if current < previous {
+                report
+                    .out_of_order
+                    .push((timestamp(previous), timestamp(current)));
+            } else if current.saturating_sub(previous) > max_interval {
+                report.gaps.push(TimeGap {
+                    start: timestamp(previous),
+                    end: timestamp(current),
+                });
+            }
+        }
+        report
+    }
+
+    /// Whether no problem was found
+    pub fn is_ok(&self) -> bool {
+        self.gaps.is_empty() && self.duplicates.is_empty() && self.out_of_order.is_empty()
+    }
+}
+
+/// The duration in units of the precision, saturated to `i64::MAX`
+fn duration_units(duration: Duration, precision: TimestampPrecision) -> i64 {
+    let units = duration.as_nanos() * precision.units_per_second() as u128 / 1_000_000_000;
+    i64::try_from(units).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_numeric_and_text_columns() {
+        let precision = TimestampPrecision::default();
+        let values = ColumnValues::Double(vec![None, Some(2.0), Some(f64::NAN), Some(4.0), None]);
+        let stats = ColumnStats::new("s1", &values, &[0, 1, 2, 3, 4], 1, precision);
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.null_count, 1);
+        assert_eq!(stats.min, Some(Value::Double(2.0)));
+        assert_eq!(stats.max, Some(Value::Double(4.0)));
+        assert!(stats.mean.unwrap().is_nan());
+        assert_eq!(
+            stats.first,
+            Some((Timestamp::new(1, precision), Value::Double(2.0)))
+        );
+
+        let values = ColumnValues::Int32(vec![Some(2), Some(4), Some(4), Some(6)]);
+        let stats = ColumnStats::new("s2", &values, &[0, 1, 2, 3], 0, precision);
+        assert_eq!(stats.mean, Some(4.0));
+        assert_eq!(stats.stddev, Some(2f64.sqrt()));
+
+        let values = ColumnValues::Text {
+            data: b"bab".to_vec(),
+            ranges: vec![Some((0, 1)), Some((1, 2)), None],
+        };
+        let stats = ColumnStats::new("s3", &values, &[0, 1, 2], 0, precision);
+        assert_eq!(stats.min, Some(Value::from("a")));
+        assert_eq!(
+            stats.last,
+            Some((Timestamp::new(1, precision), Value::from("a")))
+        );
+        assert_eq!(stats.mean, None);
+    }
+
+    #[test]
+    fn check_time_finds_gaps_duplicates_and_disorder() {
+        let precision = TimestampPrecision::Millisecond;
+        let timestamp = |value| Timestamp::new(value, precision);
+        let report = TimeReport::check(
+            &[0, 1000, 1000, 5000, 4000, 5000],
+            precision,
+            Duration::from_secs(1),
+        );
+        assert_eq!(
+            report.gaps,
+            [TimeGap {
+                start: timestamp(1000),
+                end: timestamp(5000),
+            }]
+        );
+        assert_eq!(report.duplicates, [timestamp(1000)]);
+        assert_eq!(report.out_of_order, [(timestamp(5000), timestamp(4000))]);
+        assert!(!report.is_ok());
+        assert!(TimeReport::check(&[0, 1000], precision, Duration::from_secs(1)).is_ok());
+    }
+}
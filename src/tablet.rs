@@ -0,0 +1,335 @@
+//! Tablets, rows of one device written in a single request
+
+use crate::ds::ColumnValues;
+use crate::errors::IotDBError;
+use crate::value::Value;
+use crate::DataType;
+
+/// Rows of one device with typed columns, written with
+/// [`Session::write_tablet`](crate::Session::write_tablet).
+///
+/// Null values are allowed, rows don't need to be added in time order.
+///
+/// ```
+/// use iotdb::{DataType, Tablet, Value};
+///
+/// let mut tablet = Tablet::new(
+///     "root.sg.d1",
+///     &[("temperature", DataType::FLOAT), ("status", DataType::TEXT)],
+///     1024,
+/// );
+/// tablet.add_row(1000, vec![Value::Float(36.5), Value::from("ok")]).unwrap();
+/// tablet.add_row(2000, vec![Value::Null, Value::from("off")]).unwrap();
+/// assert_eq!(tablet.len(), 2);
+/// assert!(!tablet.is_full());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tablet {
+    device_id: String,
+    measurements: Vec<String>,
+    data_types: Vec<DataType>,
+    timestamps: Vec<i64>,
+    columns: Vec<ColumnValues>,
+    capacity: usize,
+}
+
+impl Tablet {
+    /// Empty tablet of the device with measurements named relative to it, e.g. `s1`
+    pub fn new<S: AsRef<str>>(
+        device_id: &str,
+        measurements: &[(S, DataType)],
+        capacity: usize,
+    ) -> Self {
+        Self {
+            device_id: device_id.to_string(),
+            measurements: measurements
+                .iter()
+                .map(|(name, _)| name.as_ref().to_string())
+                .collect(),
+            data_types: measurements
+                .iter()
+                .map(|(_, data_type)| *data_type)
+                .collect(),
+            timestamps: Vec::with_capacity(capacity),
+            columns: measurements
+                .iter()
+                .map(|(_, data_type)| ColumnValues::new(*data_type))
+                .collect(),
+            capacity,
+        }
+    }
+
+    /// Tablet of decoded columns, full at its current size
+    #[cfg(feature = "arrow")]
+    pub(crate) fn from_columns(
+        device_id: &str,
+        measurements: Vec<String>,
+        timestamps: Vec<i64>,
+        columns: Vec<ColumnValues>,
+    ) -> Self {
+        Self {
+            device_id: device_id.to_string(),
+            data_types: columns.iter().map(ColumnValues::data_type).collect(),
+            measurements,
+            capacity: timestamps.len(),
+            timestamps,
+            columns,
+        }
+    }
+
+    /// Add a row with one value per measurement, the tablet is left unchanged on error.
+    ///
+    /// Values need the type of their measurement, INT32 and FLOAT are widened into INT64 and
+    /// DOUBLE measurements, [`Value::Null`] leaves the cell empty.
+    pub fn add_row(&mut self, timestamp: i64, values: Vec<Value>) -> Result<(), IotDBError> {
+        if self.is_full() {
+            return Err(IotDBError::Conversion(format!(
+                "tablet of {} is full with {} rows",
+                self.device_id, self.capacity
+            )));
+        }
+        if values.len() != self.measurements.len() {
+            return Err(IotDBError::Conversion(format!(
+                "row has {} values for {} measurements",
+                values.len(),
+                self.measurements.len()
+            )));
+        }
+        if let Some(((measurement, data_type), value)) = self
+            .measurements
+            .iter()
+            .zip(&self.data_types)
+            .zip(&values)
+            .find(|((_, data_type), value)| !accepts(**data_type, value))
+        {
+            return Err(IotDBError::Conversion(format!(
+                "can't add {:?} to {:?} measurement '{}'",
+                value, data_type, measurement
+            )));
+        }
+
+        self.timestamps.push(timestamp);
+        self.columns
+            .iter_mut()
+            .zip(values)
+            .for_each(|(column, value)| push_value(column, value));
+        Ok(())
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    pub fn measurements(&self) -> &[String] {
+        &self.measurements
+    }
+
+    pub fn data_types(&self) -> &[DataType] {
+        &self.data_types
+    }
+
+    pub fn timestamps(&self) -> &[i64] {
+        &self.timestamps
+    }
+
+    #[cfg(all(test, feature = "arrow"))]
+    pub(crate) fn columns(&self) -> &[ColumnValues] {
+        &self.columns
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Rows the tablet holds at most
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity
+    }
+
+    /// Drop all rows, e.g. to reuse the tablet after it's written
+    pub fn clear(&mut self) {
+        self.timestamps.clear();
+        self.columns.iter_mut().for_each(ColumnValues::clear);
+    }
+
+    /// Big-endian timestamps in time order, the `timestamps` of `TSInsertTabletReq`
+    pub fn serialize_timestamps(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(self.len() * 8);
+        self.sorted_rows()
+            .iter()
+            .for_each(|row| buffer.extend_from_slice(&self.timestamps[*row].to_be_bytes()));
+        buffer
+    }
+
+    /// Big-endian values column by column in time order, the `values` of `TSInsertTabletReq`.
+    ///
+    /// Empty cells are written as zero values, if any column has one every column is followed
+    /// by a flag and a bitmap whose set bits mark the empty cells.
+    pub fn serialize_values(&self) -> Vec<u8> {
+        let order = self.sorted_rows();
+        let mut buffer = vec![];
+        for column in &self.columns {
+            write_values(column, &order, &mut buffer);
+        }
+
+        let bitmaps: Vec<Option<Vec<u8>>> = self
+            .columns
+            .iter()
+            .map(|column| null_bitmap(column, &order))
+            .collect();
+        if bitmaps.iter().any(Option::is_some) {
+            for bitmap in bitmaps {
+                match bitmap {
+                    None => buffer.push(0),
+                    Some(bitmap) => {
+                        buffer.push(1);
+                        buffer.extend(bitmap);
+                    }
+                }
+            }
+        }
+        buffer
+    }
+
+    /// Row indexes in time order, rows with equal timestamps keep their order
+    fn sorted_rows(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by_key(|row| self.timestamps[*row]);
+        order
+    }
+}
+
+fn accepts(data_type: DataType, value: &Value) -> bool {
+    matches!(
+        (data_type, value),
+        (_, Value::Null)
+            | (DataType::BOOLEAN, Value::Bool(_))
+            | (DataType::INT32, Value::Int32(_))
+            | (DataType::INT64, Value::Int32(_) | Value::Int64(_))
+            | (DataType::FLOAT, Value::Float(_))
+            | (DataType::DOUBLE, Value::Float(_) | Value::Double(_))
+            | (DataType::TEXT, Value::Text(_))
+    )
+}
+
+/// Append a value which the column accepts, see [`accepts`]
+fn push_value(column: &mut ColumnValues, value: Value) {
+    match (column, value) {
+        (ColumnValues::Boolean(values), Value::Bool(value)) => values.push(Some(value)),
+        (ColumnValues::Int32(values), Value::Int32(value)) => values.push(Some(value)),
+        (ColumnValues::Int64(values), Value::Int32(value)) => values.push(Some(value as i64)),
+        (ColumnValues::Int64(values), Value::Int64(value)) => values.push(Some(value)),
+        (ColumnValues::Float(values), Value::Float(value)) => values.push(Some(value)),
+        (ColumnValues::Double(values), Value::Float(value)) => values.push(Some(value as f64)),
+        (ColumnValues::Double(values), Value::Double(value)) => values.push(Some(value)),
+        (ColumnValues::Text { data, ranges }, Value::Text(value)) => {
+            let start = data.len();
+            data.extend_from_slice(&value);
+            ranges.push(Some((start, data.len())));
+        }
+        (ColumnValues::Boolean(values), _) => values.push(None),
+        (ColumnValues::Int32(values), _) => values.push(None),
+        (ColumnValues::Int64(values), _) => values.push(None),
+        (ColumnValues::Float(values), _) => values.push(None),
+        (ColumnValues::Double(values), _) => values.push(None),
+        (ColumnValues::Text { ranges, .. }, _) => ranges.push(None),
+    }
+}
+
+/// Append the big-endian values of the rows in `order`, empty cells are written as zero values
+fn write_values(column: &ColumnValues, order: &[usize], buffer: &mut Vec<u8>) {
+    match column {
+        ColumnValues::Boolean(values) => order
+            .iter()
+            .for_each(|row| buffer.push((values[*row] == Some(true)) as u8)),
+        ColumnValues::Int32(values) => order.iter().for_each(|row| {
+            buffer.extend_from_slice(&values[*row].unwrap_or_default().to_be_bytes())
+        }),
+        ColumnValues::Int64(values) => order.iter().for_each(|row| {
+            buffer.extend_from_slice(&values[*row].unwrap_or_default().to_be_bytes())
+        }),
+        ColumnValues::Float(values) => order.iter().for_each(|row| {
+            buffer.extend_from_slice(&values[*row].unwrap_or_default().to_be_bytes())
+        }),
+        ColumnValues::Double(values) => order.iter().for_each(|row| {
+            buffer.extend_from_slice(&values[*row].unwrap_or_default().to_be_bytes())
+        }),
+        ColumnValues::Text { data, ranges } => order.iter().for_each(|row| {
+            let binary = ranges[*row].map_or(&[][..], |(start, end)| &data[start..end]);
+            buffer.extend_from_slice(&(binary.len() as i32).to_be_bytes());
+            buffer.extend_from_slice(binary);
+        }),
+    }
+}
+
+/// Bitmap of the empty cells of the rows in `order`, `None` if there are none
+fn null_bitmap(column: &ColumnValues, order: &[usize]) -> Option<Vec<u8>> {
+    if !order.iter().any(|row| column.is_null(*row)) {
+        return None;
+    }
+    let mut bitmap = vec![0u8; order.len() / 8 + 1];
+    for (position, row) in order.iter().enumerate() {
+        if column.is_null(*row) {
+            bitmap[position / 8] |= 1 << (position % 8);
+        }
+    }
+    Some(bitmap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_sorts_rows_and_marks_nulls() {
+        let mut tablet = Tablet::new(
+            "root.sg.d1",
+            &[("s1", DataType::INT64), ("s2", DataType::TEXT)],
+            2,
+        );
+        tablet
+            .add_row(2, vec![Value::Int32(20), Value::from("b")])
+            .unwrap();
+        tablet
+            .add_row(1, vec![Value::Null, Value::from("a")])
+            .unwrap();
+        assert!(tablet.is_full());
+        assert!(tablet.add_row(3, vec![Value::Null, Value::Null]).is_err());
+
+        assert_eq!(
+            tablet.serialize_timestamps(),
+            [1i64, 2].map(i64::to_be_bytes).concat()
+        );
+        let mut values = vec![];
+        values.extend_from_slice(&0i64.to_be_bytes());
+        values.extend_from_slice(&20i64.to_be_bytes());
+        values.extend_from_slice(&1i32.to_be_bytes());
+        values.push(b'a');
+        values.extend_from_slice(&1i32.to_be_bytes());
+        values.push(b'b');
+        // s1 is null in the first sorted row, s2 has no nulls
+        values.extend_from_slice(&[1, 0b0000_0001, 0]);
+        assert_eq!(tablet.serialize_values(), values);
+
+        tablet.clear();
+        assert!(tablet.is_empty());
+        assert!(tablet.serialize_values().is_empty());
+    }
+
+    #[test]
+    fn add_row_rejects_mismatched_values() {
+        let mut tablet = Tablet::new("root.sg.d1", &[("s1", DataType::INT32)], 10);
+        assert!(tablet.add_row(1, vec![Value::Int64(1)]).is_err());
+        assert!(tablet.add_row(1, vec![]).is_err());
+        assert!(tablet.is_empty());
+    }
+}
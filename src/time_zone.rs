@@ -0,0 +1,196 @@
+//! Time zones of sessions in the notations accepted by IoTDB
+
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::timestamp::Timestamp;
+
+/// Time zone used to display and export timestamps, parsed from the session time zone.
+///
+/// Offsets are written as `UTC+8`, `GMT-03:30`, `+08:00` or `+0800`, other names are looked
+/// up in the IANA database, e.g. `Asia/Shanghai`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SessionTimeZone {
+    Offset(FixedOffset),
+    Named(Tz),
+}
+
+impl Default for SessionTimeZone {
+    fn default() -> Self {
+        SessionTimeZone::Offset(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+impl SessionTimeZone {
+    /// Format `timestamp` in this zone with a `strftime` pattern, e.g. `%Y-%m-%d %H:%M:%S%.3f`,
+    /// or like `2022-01-01 08:00:00.123 +08:00` without one.
+    ///
+    /// Timestamps out of the chrono range and invalid patterns fall back to the raw value.
+    pub fn format(&self, timestamp: Timestamp, format: Option<&str>) -> String {
+        let mut buffer = String::new();
+        self.write(timestamp, format, &mut buffer);
+        buffer
+    }
+
+    /// RFC 3339 in this zone with all digits of the timestamp precision
+    pub fn to_rfc3339(&self, timestamp: Timestamp) -> Option<String> {
+        match self {
+            SessionTimeZone::Offset(offset) => timestamp.to_rfc3339(offset),
+            SessionTimeZone::Named(tz) => timestamp.to_rfc3339(tz),
+        }
+    }
+
+    pub(crate) fn write(&self, timestamp: Timestamp, format: Option<&str>, buffer: &mut String) {
+        let start = buffer.len();
+        let written = timestamp.to_datetime().is_some_and(|date_time| {
+            match self {
+                SessionTimeZone::Offset(offset) => write_in(date_time, offset, format, buffer),
+                SessionTimeZone::Named(tz) => write_in(date_time, tz, format, buffer),
+            }
+            .is_ok()
+        });
+        if !written {
+            buffer.truncate(start);
+            write!(buffer, "{}", timestamp.value()).unwrap();
+        }
+    }
+}
+
+fn write_in<Tz: TimeZone>(
+    date_time: DateTime<Utc>,
+    zone: &Tz,
+    format: Option<&str>,
+    buffer: &mut String,
+) -> fmt::Result
+where
+    Tz::Offset: fmt::Display,
+{
+    let date_time = date_time.with_timezone(zone);
+    match format {
+        None => write!(buffer, "{}", date_time),
+        Some(format) => write!(buffer, "{}", date_time.format(format)),
+    }
+}
+
+/// Whether `format` only holds valid `strftime` specifiers
+pub(crate) fn is_valid_time_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+}
+
+/// Parse `+8`, `-03:30` or `+0800` into an offset, Java `ZoneOffset` limits it to 18 hours
+fn parse_offset(offset: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match offset.as_bytes().first()? {
+        b'+' => (1, &offset[1..]),
+        b'-' => (-1, &offset[1..]),
+        _ => return None,
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return None;
+    }
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((hours, minutes)) if minutes.len() == 2 => (hours, minutes),
+        Some(_) => return None,
+        None if digits.len() <= 2 => (digits, "0"),
+        None if digits.len() == 4 => digits.split_at(2),
+        None => return None,
+    };
+    let (hours, minutes) = (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?);
+    if hours > 18 || minutes > 59 || hours * 60 + minutes > 18 * 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl FromStr for SessionTimeZone {
+    type Err = String;
+
+    fn from_str(time_zone: &str) -> Result<Self, Self::Err> {
+        let time_zone = time_zone.trim();
+        let offset = match time_zone.get(..3) {
+            Some(prefix)
+                if prefix.eq_ignore_ascii_case("UTC") || prefix.eq_ignore_ascii_case("GMT") =>
+            {
+                Some(&time_zone[3..])
+            }
+            _ if time_zone.starts_with(['+', '-']) => Some(time_zone),
+            _ if time_zone.eq_ignore_ascii_case("Z") => Some(""),
+            _ => None,
+        };
+        match offset {
+            Some("") => Ok(SessionTimeZone::default()),
+            Some(offset) => parse_offset(offset)
+                .map(SessionTimeZone::Offset)
+                .ok_or_else(|| format!("This '{}' time zone offset is invalid", time_zone)),
+            None => time_zone
+                .parse::<Tz>()
+                .map(SessionTimeZone::Named)
+                .map_err(|_| format!("This '{}' time zone doesn't exist", time_zone)),
+        }
+    }
+}
+
+impl fmt::Display for SessionTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionTimeZone::Offset(offset) => write!(f, "{}", offset),
+            SessionTimeZone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::TimestampPrecision;
+
+    #[test]
+    fn parse_offsets_and_names() {
+        let east_8 = SessionTimeZone::Offset(FixedOffset::east_opt(8 * 3600).unwrap());
+        for time_zone in ["UTC+8", "utc+08:00", "GMT+0800", "+08:00", " +8 "] {
+            assert_eq!(time_zone.parse::<SessionTimeZone>(), Ok(east_8));
+        }
+        assert_eq!(
+            "UTC-03:30".parse::<SessionTimeZone>().unwrap().to_string(),
+            "-03:30"
+        );
+        assert_eq!("UTC".parse(), Ok(SessionTimeZone::default()));
+        assert_eq!(
+            "Asia/Shanghai".parse(),
+            Ok(SessionTimeZone::Named(chrono_tz::Asia::Shanghai))
+        );
+        for time_zone in ["UTC+19", "+08:0", "+123", "Mars/Olympus"] {
+            assert!(
+                time_zone.parse::<SessionTimeZone>().is_err(),
+                "{}",
+                time_zone
+            );
+        }
+    }
+
+    #[test]
+    fn format_in_zone() {
+        let timestamp = Timestamp::new(1_656_669_600_123, TimestampPrecision::Millisecond);
+        let berlin = SessionTimeZone::Named(chrono_tz::Europe::Berlin);
+        assert_eq!(
+            berlin.format(timestamp, Some("%Y-%m-%d %H:%M:%S%.3f %Z")),
+            "2022-07-01 12:00:00.123 CEST"
+        );
+        assert_eq!(
+            berlin.to_rfc3339(timestamp).unwrap(),
+            "2022-07-01T12:00:00.123+02:00"
+        );
+        assert_eq!(
+            "UTC+8"
+                .parse::<SessionTimeZone>()
+                .unwrap()
+                .format(timestamp, None),
+            "2022-07-01 18:00:00.123 +08:00"
+        );
+        assert!(!is_valid_time_format("%Y-%Q"));
+        assert_eq!(berlin.format(timestamp, Some("%Y-%Q")), "1656669600123");
+    }
+}
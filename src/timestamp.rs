@@ -0,0 +1,185 @@
+//! Timestamps in the precision of the server
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+
+/// Unit of the timestamps stored by the server, `timestamp_precision` in `iotdb-engine.properties`
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum TimestampPrecision {
+    #[default]
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+}
+
+impl TimestampPrecision {
+    /// Number of units per second
+    pub fn units_per_second(&self) -> i64 {
+        match self {
+            TimestampPrecision::Millisecond => 1_000,
+            TimestampPrecision::Microsecond => 1_000_000,
+            TimestampPrecision::Nanosecond => 1_000_000_000,
+        }
+    }
+
+    pub(crate) fn seconds_format(&self) -> SecondsFormat {
+        match self {
+            TimestampPrecision::Millisecond => SecondsFormat::Millis,
+            TimestampPrecision::Microsecond => SecondsFormat::Micros,
+            TimestampPrecision::Nanosecond => SecondsFormat::Nanos,
+        }
+    }
+}
+
+impl FromStr for TimestampPrecision {
+    type Err = String;
+
+    fn from_str(precision: &str) -> Result<Self, Self::Err> {
+        match precision {
+            "ms" => Ok(TimestampPrecision::Millisecond),
+            "us" => Ok(TimestampPrecision::Microsecond),
+            "ns" => Ok(TimestampPrecision::Nanosecond),
+            _ => Err(format!(
+                "This '{}' timestamp precision doesn't exist",
+                precision
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TimestampPrecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimestampPrecision::Millisecond => write!(f, "ms"),
+            TimestampPrecision::Microsecond => write!(f, "us"),
+            TimestampPrecision::Nanosecond => write!(f, "ns"),
+        }
+    }
+}
+
+/// A raw IoTDB timestamp together with its precision
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Timestamp {
+    value: i64,
+    precision: TimestampPrecision,
+}
+
+impl Timestamp {
+    pub fn new(value: i64, precision: TimestampPrecision) -> Self {
+        Self { value, precision }
+    }
+
+    /// Convert a date time into a timestamp of the given precision, `None` if it's out of range
+    pub fn from_datetime<Tz: TimeZone>(
+        date_time: &DateTime<Tz>,
+        precision: TimestampPrecision,
+    ) -> Option<Self> {
+        let value = match precision {
+            TimestampPrecision::Millisecond => Some(date_time.timestamp_millis()),
+            TimestampPrecision::Microsecond => Some(date_time.timestamp_micros()),
+            TimestampPrecision::Nanosecond => date_time.timestamp_nanos_opt(),
+        }?;
+        Some(Self::new(value, precision))
+    }
+
+    /// The raw value as stored by the server
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    pub fn precision(&self) -> TimestampPrecision {
+        self.precision
+    }
+
+    /// The same instant in another precision, truncated towards the past when the precision
+    /// is coarser, `None` if it overflows
+    pub fn to_precision(&self, precision: TimestampPrecision) -> Option<Self> {
+        let from = self.precision.units_per_second();
+        let to = precision.units_per_second();
+        let value = match to >= from {
+            true => self.value.checked_mul(to / from)?,
+            false => self.value.div_euclid(from / to),
+        };
+        Some(Self::new(value, precision))
+    }
+
+    /// Milliseconds since the unix epoch, sub-millisecond digits are truncated towards the past
+    pub fn to_millis(&self) -> i64 {
+        self.value
+            .div_euclid(self.precision.units_per_second() / 1_000)
+    }
+
+    pub fn to_datetime(&self) -> Option<DateTime<Utc>> {
+        let units = self.precision.units_per_second();
+        let nanos = self.value.rem_euclid(units) * (1_000_000_000 / units);
+        Utc.timestamp_opt(self.value.div_euclid(units), nanos as u32)
+            .single()
+    }
+
+    /// RFC 3339 in the given time zone with all digits of the precision
+    pub fn to_rfc3339<Tz: TimeZone>(&self, time_zone: &Tz) -> Option<String>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        self.to_datetime().map(|date_time| {
+            date_time
+                .with_timezone(time_zone)
+                .to_rfc3339_opts(self.precision.seconds_format(), true)
+        })
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_rfc3339(&Utc) {
+            None => write!(f, "{}{}", self.value, self.precision),
+            Some(date_time) => write!(f, "{}", date_time),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nanosecond_timestamp_to_datetime() {
+        let timestamp = Timestamp::new(1_640_995_200_123_456_789, TimestampPrecision::Nanosecond);
+        assert_eq!(timestamp.to_millis(), 1_640_995_200_123);
+        assert_eq!(
+            timestamp.to_string(),
+            "2022-01-01T00:00:00.123456789Z".to_string()
+        );
+        assert_eq!(
+            Timestamp::from_datetime(&timestamp.to_datetime().unwrap(), timestamp.precision()),
+            Some(timestamp)
+        );
+    }
+
+    #[test]
+    fn negative_microsecond_timestamp_to_millis() {
+        let timestamp = Timestamp::new(-1, TimestampPrecision::Microsecond);
+        assert_eq!(timestamp.to_millis(), -1);
+        assert_eq!(timestamp.to_string(), "1969-12-31T23:59:59.999999Z");
+    }
+
+    #[test]
+    fn to_precision_rounds_and_overflows() {
+        let timestamp = Timestamp::new(-1, TimestampPrecision::Microsecond);
+        assert_eq!(
+            timestamp.to_precision(TimestampPrecision::Millisecond),
+            Some(Timestamp::new(-1, TimestampPrecision::Millisecond))
+        );
+        assert_eq!(
+            timestamp.to_precision(TimestampPrecision::Nanosecond),
+            Some(Timestamp::new(-1_000, TimestampPrecision::Nanosecond))
+        );
+        assert_eq!(
+            Timestamp::new(i64::MAX, TimestampPrecision::Millisecond)
+                .to_precision(TimestampPrecision::Nanosecond),
+            None
+        );
+    }
+}
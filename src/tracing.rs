@@ -0,0 +1,183 @@
+//! Tracing info of `TRACING` queries
+
+use std::fmt;
+
+use prettytable::Row as PrettyRow;
+use prettytable::Table;
+
+use crate::client::TSTracingInfo;
+
+/// One step of a traced query
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Activity {
+    pub name: String,
+    /// Milliseconds since the statement started, `None` if the server sent no time for it
+    pub elapsed_time: Option<i64>,
+}
+
+/// Statistics the server collects for a `TRACING select ...` statement
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TracingInfo {
+    pub activities: Vec<Activity>,
+    pub series_path_num: Option<i32>,
+    pub seq_file_num: Option<i32>,
+    pub un_seq_file_num: Option<i32>,
+    pub sequence_chunk_num: Option<i32>,
+    pub sequence_chunk_point_num: Option<i64>,
+    pub unsequence_chunk_num: Option<i32>,
+    pub unsequence_chunk_point_num: Option<i64>,
+    pub total_page_num: Option<i32>,
+    pub overlapped_page_num: Option<i32>,
+}
+
+impl From<TSTracingInfo> for TracingInfo {
+    fn from(info: TSTracingInfo) -> Self {
+        Self {
+            activities: info
+                .activity_list
+                .into_iter()
+                .enumerate()
+                .map(|(index, name)| Activity {
+                    name,
+                    elapsed_time: info.elapsed_time_list.get(index).copied(),
+                })
+                .collect(),
+            series_path_num: info.series_path_num,
+            seq_file_num: info.seq_file_num,
+            un_seq_file_num: info.un_seq_file_num,
+            sequence_chunk_num: info.sequence_chunk_num,
+            sequence_chunk_point_num: info.sequence_chunk_point_num,
+            unsequence_chunk_num: info.unsequence_chunk_num,
+            unsequence_chunk_point_num: info.unsequence_chunk_point_num,
+            total_page_num: info.total_page_num,
+            overlapped_page_num: info.overlapped_page_num,
+        }
+    }
+}
+
+impl TracingInfo {
+    /// Total milliseconds of the query, the elapsed time of the last activity
+    pub fn elapsed_time(&self) -> Option<i64> {
+        self.activities
+            .last()
+            .and_then(|activity| activity.elapsed_time)
+    }
+
+    fn counters(&self) -> [(&'static str, Option<i64>); 9] {
+        [
+            ("Series paths", self.series_path_num.map(i64::from)),
+            ("Sequence files", self.seq_file_num.map(i64::from)),
+            ("Unsequence files", self.un_seq_file_num.map(i64::from)),
+            ("Sequence chunks", self.sequence_chunk_num.map(i64::from)),
+            ("Sequence chunk points", self.sequence_chunk_point_num),
+            (
+                "Unsequence chunks",
+                self.unsequence_chunk_num.map(i64::from),
+            ),
+            ("Unsequence chunk points", self.unsequence_chunk_point_num),
+            ("Total pages", self.total_page_num.map(i64::from)),
+            ("Overlapped pages", self.overlapped_page_num.map(i64::from)),
+        ]
+    }
+
+    fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        table.set_titles(PrettyRow::new(vec![cell!("Tracing"), cell!("Value")]));
+        self.activities.iter().for_each(|activity| {
+            table.add_row(PrettyRow::new(vec![
+                cell!(activity.name),
+                cell!(activity
+                    .elapsed_time
+                    .map(|elapsed_time| format!("{}ms", elapsed_time))
+                    .unwrap_or_default()),
+            ]));
+        });
+        self.counters()
+            .iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .for_each(|(name, value)| {
+                table.add_row(PrettyRow::new(vec![cell!(name), cell!(value)]));
+            });
+        table
+    }
+
+    pub fn show(&self) {
+        self.table().printstd();
+    }
+}
+
+impl fmt::Display for TracingInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.table())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_tracing_info_and_display() {
+        let info = TSTracingInfo::new(
+            vec![
+                "Parse".to_string(),
+                "Plan".to_string(),
+                "Execute".to_string(),
+            ],
+            vec![1, 3],
+            2,
+            4,
+            1,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let tracing = TracingInfo::from(info);
+
+        assert_eq!(
+            tracing.activities,
+            [
+                Activity {
+                    name: "Parse".to_string(),
+                    elapsed_time: Some(1)
+                },
+                Activity {
+                    name: "Plan".to_string(),
+                    elapsed_time: Some(3)
+                },
+                Activity {
+                    name: "Execute".to_string(),
+                    elapsed_time: None
+                },
+            ]
+        );
+        assert_eq!(tracing.elapsed_time(), None);
+        assert_eq!(tracing.series_path_num, Some(2));
+        assert_eq!(tracing.seq_file_num, Some(4));
+        assert_eq!(tracing.un_seq_file_num, Some(1));
+        assert_eq!(tracing.sequence_chunk_num, None);
+
+        let rows: Vec<String> = tracing
+            .to_string()
+            .lines()
+            .filter(|line| line.starts_with('|'))
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "| Tracing | Value |",
+                "| Parse | 1ms |",
+                "| Plan | 3ms |",
+                "| Execute | |",
+                "| Series paths | 2 |",
+                "| Sequence files | 4 |",
+                "| Unsequence files | 1 |",
+            ]
+        );
+    }
+}
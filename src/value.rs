@@ -0,0 +1,214 @@
+use std::fmt;
+
+use crate::errors::IotDBError;
+use crate::DataType;
+
+/// A single value of a query result
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int32(i32),
+    Int64(i64),
+    Float(f32),
+    Double(f64),
+    Text(Vec<u8>),
+    Null,
+}
+
+impl Value {
+    /// Data type of the value, `None` for null
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            Value::Bool(_) => Some(DataType::BOOLEAN),
+            Value::Int32(_) => Some(DataType::INT32),
+            Value::Int64(_) => Some(DataType::INT64),
+            Value::Float(_) => Some(DataType::FLOAT),
+            Value::Double(_) => Some(DataType::DOUBLE),
+            Value::Text(_) => Some(DataType::TEXT),
+            Value::Null => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "BOOLEAN",
+            Value::Int32(_) => "INT32",
+            Value::Int64(_) => "INT64",
+            Value::Float(_) => "FLOAT",
+            Value::Double(_) => "DOUBLE",
+            Value::Text(_) => "TEXT",
+            Value::Null => "NULL",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int32(value) => write!(f, "{}", value),
+            Value::Int64(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Double(value) => write!(f, "{}", value),
+            Value::Text(value) => write!(f, "{}", String::from_utf8_lossy(value)),
+            Value::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int32(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int64(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Double(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// Conversion from a query [`Value`] into a Rust type.
+///
+/// Integers and floats are widened losslessly (`INT32` into `i64`, `FLOAT` into `f64`),
+/// any other type mismatch or a null value is a [`IotDBError::Conversion`],
+/// use `Option<T>` to accept nulls.
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, IotDBError>;
+}
+
+fn mismatch(value: &Value, target: &str) -> IotDBError {
+    IotDBError::Conversion(format!(
+        "can't convert {} value '{}' into {}",
+        value.type_name(),
+        value,
+        target
+    ))
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        Ok(value.clone())
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Bool(value) => Ok(*value),
+            _ => Err(mismatch(value, "bool")),
+        }
+    }
+}
+
+impl FromValue for i32 {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Int32(value) => Ok(*value),
+            _ => Err(mismatch(value, "i32")),
+        }
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Int32(value) => Ok(*value as i64),
+            Value::Int64(value) => Ok(*value),
+            _ => Err(mismatch(value, "i64")),
+        }
+    }
+}
+
+impl FromValue for f32 {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Float(value) => Ok(*value),
+            _ => Err(mismatch(value, "f32")),
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Float(value) => Ok(*value as f64),
+            Value::Double(value) => Ok(*value),
+            _ => Err(mismatch(value, "f64")),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Text(binary) => String::from_utf8(binary.clone())
+                .map_err(|_| IotDBError::Conversion("TEXT value is not valid UTF-8".to_string())),
+            _ => Err(mismatch(value, "String")),
+        }
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Text(binary) => Ok(binary.clone()),
+            _ => Err(mismatch(value, "Vec<u8>")),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, IotDBError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}